@@ -15,13 +15,18 @@ echo "🚀 Binary sistem yoluna taşınıyor..."
 sudo cp target/release/focus /usr/local/bin/
 
 echo "📂 Config klasörü kontrol ediliyor..."
-# Config klasörü yoksa oluştur
-if [ ! -d "/etc/focus" ]; then
-    sudo mkdir -p /etc/focus
-    # Config dosyası yoksa boş bir JSON oluştur ki hata vermesin
-    if [ ! -f "/etc/focus/config.json" ]; then
-        echo '{"rules": []}' | sudo tee /etc/focus/config.json > /dev/null
-    fi
+# Config dosyası yoksa ilk değişiklikte focus kendisi oluşturur
+sudo mkdir -p /etc/focus/conf.d
+if [ -f "/etc/focus/config.toml" ] || [ -f "/etc/focus/config.json" ]; then
+    # Eski sürüm configler (config.json) daemon başlarken yedeklenip config.toml'a çevrilir
+    sudo /usr/local/bin/focus config validate || echo "⚠️  Config hatalı, yukarıdaki sorunları düzeltin."
+fi
+
+echo "👥 focus grubu kontrol ediliyor..."
+# Bu grubun üyeleri sudo olmadan kural ekleyip istisna kullanabilir
+sudo groupadd -f focus
+if [ -n "$SUDO_USER" ] || [ "$USER" != "root" ]; then
+    sudo usermod -aG focus "${SUDO_USER:-$USER}"
 fi
 
 echo "⚙️ Systemd servisi oluşturuluyor/güncelleniyor..."
@@ -47,4 +52,4 @@ sudo systemctl enable focus.service
 sudo systemctl restart focus.service
 
 echo "✅ Kurulum tamamlandı! Focus arkaplanda çalışıyor."
-echo "   Test: sudo focus list"
+echo "   Test: focus list (grup üyeliği için oturumu yeniden açmanız gerekebilir)"
@@ -1,19 +1,11 @@
 #!/bin/bash
-# Hata olsa bile devam et
+# focus'un eklediği güvenlik duvarı kurallarını günlükten (/run/focus/firewall.json) birebir kaldırır.
+# Daemon çalışıyorsa engelleme hemen yeniden uygulanır.
 set +e
 
-# IPv4 Temizliği
-sudo iptables -w -D OUTPUT -p tcp --dport 443 -j REJECT --reject-with tcp-reset 2>/dev/null
-sudo iptables -w -D OUTPUT -p udp --dport 443 -j REJECT --reject-with icmp-port-unreachable 2>/dev/null
-sudo iptables -w -D OUTPUT -p tcp --dport 80 -j REJECT --reject-with tcp-reset 2>/dev/null
-sudo iptables -w -D INPUT -p tcp --sport 443 -j REJECT --reject-with tcp-reset 2>/dev/null
-sudo iptables -w -D INPUT -p udp --sport 443 -j REJECT --reject-with icmp-port-unreachable 2>/dev/null
-sudo iptables -w -D INPUT -p tcp --sport 80 -j REJECT --reject-with tcp-reset 2>/dev/null
-
-# IPv6 Temizliği
-sudo ip6tables -w -D OUTPUT -p tcp --dport 443 -j REJECT --reject-with tcp-reset 2>/dev/null
-sudo ip6tables -w -D OUTPUT -p udp --dport 443 -j REJECT --reject-with icmp6-port-unreachable 2>/dev/null
-sudo ip6tables -w -D OUTPUT -p tcp --dport 80 -j REJECT --reject-with tcp-reset 2>/dev/null
-sudo ip6tables -w -D INPUT -p tcp --sport 443 -j REJECT --reject-with tcp-reset 2>/dev/null
-sudo ip6tables -w -D INPUT -p udp --sport 443 -j REJECT --reject-with icmp6-port-unreachable 2>/dev/null
-sudo ip6tables -w -D INPUT -p tcp --sport 80 -j REJECT --reject-with tcp-reset 2>/dev/null
+if command -v focus > /dev/null; then
+    sudo focus repair
+else
+    # Binary yoksa en azından focus tablosunu kaldır
+    sudo nft delete table inet focus 2>/dev/null
+fi
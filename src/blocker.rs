@@ -0,0 +1,457 @@
+// Engelleme backend'leri. Engellenecek isimler her turda bir kez hesaplanır (BlockSet)
+// ve config'te seçili tüm backend'lere uygulanır; seçili olmayanlar temizlenir.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+
+use crate::{Backend, Config, allowed_domains, blocked_domains, dns, firewall, hosts, hosts_names, schedule};
+
+// Domainlerin IP adresleri değişebildiği için belirli aralıklarla yeniden çözülür
+const NFT_RESOLVE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Belirli bir anda engellenmesi gereken her şey
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BlockSet {
+    /// Kural seviyesindeki domainler ("*.domain" olabilir)
+    pub domains: Vec<String>,
+    /// Açılmış tam isimler (domain, www., alt alan adları)
+    pub names: Vec<String>,
+    /// Tüm alt alan adlarıyla engellenen domainler ("*." öneki olmadan)
+    pub wildcards: Vec<String>,
+    /// İzin listesi aktifse yalnızca bu domainler ve alt alan adları çözülür
+    pub allowed: Option<Vec<String>>,
+}
+
+impl BlockSet {
+    pub fn compute(config: &Config, now: DateTime<Local>) -> Result<Self> {
+        let domains = blocked_domains(config, now)?;
+        // Abonelik listeleri binlerce isim içerebildiği için tekrarlar küme ile ayıklanır
+        let mut names = Vec::new();
+        let mut seen = HashSet::new();
+        for domain in &domains {
+            for name in hosts_names(config, domain) {
+                if seen.insert(name.clone()) {
+                    names.push(name);
+                }
+            }
+        }
+        let wildcards = domains
+            .iter()
+            .filter_map(|d| d.strip_prefix("*.").map(str::to_string))
+            .collect();
+        let allowed = allowed_domains(config, now)?;
+        Ok(Self { domains, names, wildcards, allowed })
+    }
+}
+
+pub trait Blocker: Send {
+    /// Config'teki backend ayarlarını alır (upstream adresi vb.)
+    fn configure(&mut self, _config: &Config) {}
+
+    /// Engel listesini uygular. Yeni bir isim/adres engellendiyse true döner
+    /// (açık bağlantıların kesilmesi gerekir).
+    fn apply(&mut self, set: &BlockSet) -> Result<bool>;
+
+    /// Backend seçili değilse bıraktığı izleri temizler
+    fn clear(&mut self) -> Result<()> {
+        self.apply(&BlockSet::default()).map(|_| ())
+    }
+}
+
+/// Config'teki backend'leri yönetir
+pub struct Blockers {
+    entries: Vec<(Backend, Box<dyn Blocker>)>,
+    in_daemon: bool,
+    last_names: Option<Vec<String>>,
+    /// İzin listesi DNS backend'i olmadan aktif olduğunda uyarı bir kez yazılır
+    allowlist_warned: bool,
+}
+
+impl Blockers {
+    /// DNS resolver yalnızca daemon içinde çalışabilir; CLI tarafında dahil edilmez.
+    /// Daemon içinde nftables adresleri arka planda çözülür.
+    pub fn new(in_daemon: bool) -> Self {
+        let mut entries: Vec<(Backend, Box<dyn Blocker>)> = vec![
+            (Backend::Hosts, Box::new(HostsBlocker::new(crate::HOSTS_PATH))),
+            (Backend::Nftables, Box::new(NftBlocker::new(in_daemon))),
+            (Backend::DryRun, Box::new(MemoryBlocker::new(true))),
+        ];
+        if in_daemon {
+            entries.push((Backend::Dns, Box::new(DnsBlocker::default())));
+        }
+        Self { entries, in_daemon, last_names: None, allowlist_warned: false }
+    }
+
+    pub fn in_daemon(&self) -> bool {
+        self.in_daemon
+    }
+
+    /// Seçili backend'lere uygular, diğerlerini temizler. Yeni engellenen isimleri döner
+    /// (önceki durum bilinmiyorsa tüm engelli isimler).
+    pub fn apply(&mut self, config: &Config, set: &BlockSet) -> Vec<String> {
+        let unenforced = set.allowed.is_some() && !config.backends.contains(&Backend::Dns);
+        if unenforced && !self.allowlist_warned {
+            eprintln!("İzin listesi aktif ama dns backend'i seçili değil, yalnızca engel kuralları uygulanıyor");
+        }
+        self.allowlist_warned = unenforced;
+
+        let mut newly_blocked = false;
+        for (backend, blocker) in self.entries.iter_mut() {
+            blocker.configure(config);
+            let result = if config.backends.contains(backend) {
+                blocker.apply(set).map(|added| newly_blocked |= added)
+            } else {
+                blocker.clear()
+            };
+            if let Err(e) = result {
+                eprintln!("{:?} Hatası: {:#}", backend, e);
+            }
+        }
+
+        let previous = self.last_names.replace(set.names.clone());
+        if !newly_blocked {
+            return vec![];
+        }
+        match previous {
+            Some(previous) => set.names.iter().filter(|n| !previous.contains(n)).cloned().collect(),
+            None => set.names.clone(),
+        }
+    }
+}
+
+/// /etc/hosts'a yazar; hosts'un yapamadığı wildcard'lar için DNS sorgularını güvenlik duvarında reddeder
+pub struct HostsBlocker {
+    path: PathBuf,
+    wildcards: Option<Vec<String>>,
+}
+
+impl HostsBlocker {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), wildcards: None }
+    }
+
+    fn block_count(&self) -> usize {
+        let content = fs::read_to_string(&self.path).unwrap_or_default();
+        content.lines().filter(|l| l.contains("127.0.0.1")).count()
+    }
+}
+
+impl Blocker for HostsBlocker {
+    fn apply(&mut self, set: &BlockSet) -> Result<bool> {
+        if self.wildcards.as_ref() != Some(&set.wildcards) {
+            firewall::sync_wildcard_rules(&set.wildcards);
+            self.wildcards = Some(set.wildcards.clone());
+        }
+
+        let old_block_count = self.block_count();
+        if !hosts::update(&self.path, &set.names)? {
+            return Ok(false);
+        }
+        Ok(self.block_count() > old_block_count)
+    }
+}
+
+/// Engelli isimlerin IP adreslerini nftables setlerinde tutar ve bu adreslere giden trafiği reddeder
+pub struct NftBlocker {
+    upstream: String,
+    /// Daemon içinde isimler arka planda çözülür; daemon durum kilidi çözüm süresince tutulmaz
+    background: bool,
+    names: Vec<String>,
+    /// Son çözümlenen adresler
+    addresses: BTreeSet<IpAddr>,
+    resolved_at: Option<Instant>,
+    /// Arka planda çözülmekte olan isimler
+    resolving: Option<Vec<String>>,
+    pending: Arc<Mutex<Resolution>>,
+    /// nft'ye en son başarıyla yüklenen adresler (None: bilinmiyor/yüklenmedi)
+    installed: Option<BTreeSet<IpAddr>>,
+}
+
+/// Arka plandaki en son çözümün sonucu; eski çözümlerin sonuçları atılır
+#[derive(Default)]
+struct Resolution {
+    generation: u64,
+    result: Option<(Vec<String>, BTreeSet<IpAddr>)>,
+}
+
+impl NftBlocker {
+    pub fn new(background: bool) -> Self {
+        Self {
+            upstream: String::new(),
+            background,
+            names: vec![],
+            addresses: BTreeSet::new(),
+            resolved_at: None,
+            resolving: None,
+            pending: Arc::default(),
+            installed: None,
+        }
+    }
+
+    /// İsimleri ayrı bir iş parçacığında çözer; bitince zamanlayıcıyı uyandırır ve
+    /// sonuç bir sonraki apply'da yüklenir
+    fn resolve_in_background(&mut self, names: &[String]) {
+        let generation = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.generation += 1;
+            pending.result = None;
+            pending.generation
+        };
+        self.resolving = Some(names.to_vec());
+
+        let (names, upstream, pending) = (names.to_vec(), self.upstream.clone(), Arc::clone(&self.pending));
+        thread::spawn(move || {
+            let addresses = dns::lookup_all(&names, &upstream);
+            let mut pending = pending.lock().unwrap();
+            if pending.generation == generation {
+                pending.result = Some((names, addresses));
+                schedule::wake();
+            }
+        });
+    }
+}
+
+impl Blocker for NftBlocker {
+    fn configure(&mut self, config: &Config) {
+        self.upstream = config.dns.upstream.clone();
+    }
+
+    fn apply(&mut self, set: &BlockSet) -> Result<bool> {
+        if let Some((names, addresses)) = self.pending.lock().unwrap().result.take() {
+            self.resolving = None;
+            self.names = names;
+            self.addresses = addresses;
+            self.resolved_at = Some(Instant::now());
+        }
+
+        // Arka planda çözülürken yeni isimlere bağlantılar hemen kesilir; adresler çözülünce eklenir
+        let mut added = false;
+        let stale = self.resolved_at.is_none_or(|t| t.elapsed() > NFT_RESOLVE_INTERVAL);
+        if set.names != self.names || stale {
+            if set.names.is_empty() || !self.background {
+                self.addresses = dns::lookup_all(&set.names, &self.upstream);
+                self.names = set.names.clone();
+                self.resolved_at = Some(Instant::now());
+            } else if self.resolving.as_ref() != Some(&set.names) {
+                added = set.names.iter().any(|n| !self.names.contains(n));
+                self.resolve_in_background(&set.names);
+            }
+        }
+        if self.installed.as_ref() == Some(&self.addresses) {
+            return Ok(added);
+        }
+
+        firewall::set_blocked_addresses(&self.addresses)?;
+
+        let previous = self.installed.replace(self.addresses.clone()).unwrap_or_default();
+        Ok(added || self.addresses.difference(&previous).next().is_some())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        // Süren bir arka plan çözümünün sonucu artık yüklenmemeli
+        if self.resolving.take().is_some() {
+            let mut pending = self.pending.lock().unwrap();
+            pending.generation += 1;
+            pending.result = None;
+        }
+        if self.installed.as_ref().is_some_and(|a| a.is_empty()) {
+            return Ok(());
+        }
+        // Tablo, bağlantı kesme setleri için kalır; yalnızca kalıcı engeller boşaltılır
+        let _ = firewall::set_blocked_addresses(&BTreeSet::new());
+        self.names.clear();
+        self.addresses.clear();
+        self.resolved_at = None;
+        self.installed = Some(BTreeSet::new());
+        Ok(())
+    }
+}
+
+/// Daemon içindeki DNS sinkhole'a engel listesini iletir
+#[derive(Default)]
+pub struct DnsBlocker {
+    settings: dns::DnsSettings,
+    names: BTreeSet<String>,
+    /// İzin listesi modu uygulanıyor mu
+    allowlist: bool,
+}
+
+impl Blocker for DnsBlocker {
+    fn configure(&mut self, config: &Config) {
+        self.settings = config.dns.clone();
+    }
+
+    fn apply(&mut self, set: &BlockSet) -> Result<bool> {
+        let list = dns::BlockList {
+            exact: set.names.iter().cloned().collect(),
+            suffixes: set.wildcards.clone(),
+            allowed: set.allowed.clone(),
+        };
+        dns::update(list, &self.settings);
+        dns::ensure_started(&self.settings)?;
+
+        let names: BTreeSet<String> = set.names.iter().cloned().collect();
+        let added = names.difference(&self.names).next().is_some() || (set.allowed.is_some() && !self.allowlist);
+        self.names = names;
+        self.allowlist = set.allowed.is_some();
+        Ok(added)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        dns::update(dns::BlockList::default(), &self.settings);
+        self.names.clear();
+        self.allowlist = false;
+        Ok(())
+    }
+}
+
+/// Hiçbir şeye dokunmadan uygulanan listeleri kaydeder (dry-run ve denemeler için)
+pub struct MemoryBlocker {
+    pub applied: Vec<Vec<String>>,
+    log: bool,
+}
+
+impl MemoryBlocker {
+    pub fn new(log: bool) -> Self {
+        Self { applied: vec![], log }
+    }
+}
+
+impl Blocker for MemoryBlocker {
+    fn apply(&mut self, set: &BlockSet) -> Result<bool> {
+        let previous = self.applied.last().cloned().unwrap_or_default();
+        if previous == set.names {
+            return Ok(false);
+        }
+
+        let added = set.names.iter().any(|n| !previous.contains(n));
+        if self.log {
+            println!("[dry-run] engellenecek: {}", if set.names.is_empty() { "-".to_string() } else { set.names.join(" ") });
+        }
+        self.applied.push(set.names.clone());
+        Ok(added)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.applied.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rule, WEEK};
+    use chrono::TimeZone;
+
+    fn rule(id: u32, domain: &str, start: &str, end: &str) -> Rule {
+        Rule {
+            id,
+            domain: domain.to_string(),
+            start_time: start.to_string(),
+            end_time: end.to_string(),
+            days: WEEK.to_vec(),
+            enabled: true,
+            source: None,
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 11, hour, 0, 0).unwrap()
+    }
+
+    fn names(names: &[&str]) -> BlockSet {
+        BlockSet { names: names.iter().map(|n| n.to_string()).collect(), ..BlockSet::default() }
+    }
+
+    #[test]
+    fn compute_expands_active_rules() {
+        let config = Config {
+            rules: vec![
+                rule(1, "youtube.com", "09:00", "17:00"),
+                rule(2, "*.example.org", "00:00", "24:00"),
+                rule(3, "reddit.com", "18:00", "20:00"),
+            ],
+            ..Config::default()
+        };
+        let set = BlockSet::compute(&config, at(10)).unwrap();
+        assert_eq!(set.domains, ["*.example.org", "youtube.com"]);
+        assert_eq!(set.wildcards, ["example.org"]);
+        assert_eq!(set.allowed, None);
+        for name in ["youtube.com", "www.youtube.com", "m.youtube.com", "example.org", "www.example.org"] {
+            assert!(set.names.contains(&name.to_string()), "{} eksik", name);
+        }
+        assert!(!set.names.iter().any(|n| n.contains("reddit")));
+
+        let set = BlockSet::compute(&config, at(19)).unwrap();
+        assert_eq!(set.domains, ["*.example.org", "reddit.com"]);
+    }
+
+    #[test]
+    fn compute_skips_disabled_and_paused_rules() {
+        let mut config = Config {
+            rules: vec![rule(1, "youtube.com", "00:00", "24:00"), rule(2, "reddit.com", "00:00", "24:00")],
+            ..Config::default()
+        };
+        config.rules[0].enabled = false;
+        config.state.paused.insert(2, at(12));
+
+        assert_eq!(BlockSet::compute(&config, at(10)).unwrap(), BlockSet::default());
+        assert_eq!(BlockSet::compute(&config, at(13)).unwrap().domains, ["reddit.com"]);
+    }
+
+    #[test]
+    fn compute_deduplicates_names() {
+        let config = Config {
+            rules: vec![rule(1, "example.org", "09:00", "12:00"), rule(2, "*.example.org", "10:00", "13:00")],
+            ..Config::default()
+        };
+        let set = BlockSet::compute(&config, at(11)).unwrap();
+        assert_eq!(set.domains, ["*.example.org", "example.org"]);
+        assert_eq!(set.names, ["example.org", "www.example.org"]);
+    }
+
+    #[test]
+    fn hosts_backend_writes_and_removes_block() {
+        let path = std::env::temp_dir().join(format!("focus-hosts-test-{}", std::process::id()));
+        let original = "127.0.0.1 localhost\n::1 localhost\n";
+        fs::write(&path, original).unwrap();
+
+        // Boş wildcard listesi zaten uygulanmış sayılır; test güvenlik duvarına dokunmaz
+        let mut blocker = HostsBlocker { path: path.clone(), wildcards: Some(vec![]) };
+        let set = names(&["example.org", "www.example.org"]);
+        assert!(blocker.apply(&set).unwrap());
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(original));
+        assert!(content.contains("127.0.0.1 example.org\n127.0.0.1 www.example.org\n"));
+
+        assert!(!blocker.apply(&set).unwrap());
+        assert!(!blocker.apply(&names(&["example.org"])).unwrap());
+        assert!(!fs::read_to_string(&path).unwrap().contains("www.example.org"));
+
+        blocker.clear().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn memory_blocker_records_applied_sets() {
+        let mut blocker = MemoryBlocker::new(false);
+        assert!(blocker.apply(&names(&["a.com", "b.com"])).unwrap());
+        assert!(!blocker.apply(&names(&["a.com", "b.com"])).unwrap());
+        assert!(!blocker.apply(&names(&["a.com"])).unwrap());
+        assert!(blocker.apply(&names(&["a.com", "c.com"])).unwrap());
+        assert_eq!(blocker.applied, [vec!["a.com", "b.com"], vec!["a.com"], vec!["a.com", "c.com"]]);
+
+        blocker.clear().unwrap();
+        assert!(blocker.applied.is_empty());
+    }
+}
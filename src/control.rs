@@ -0,0 +1,215 @@
+// CLI <-> daemon kontrol soketi.
+// Protokol: bağlantı başına tek satır JSON istek (Commands), tek satır JSON yanıt (Response).
+// Soket root:focus 0660 açılır; focus grubu üyeleri sudo olmadan sınırlı işlemler yapabilir.
+
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{AllowlistAction, BwAction, Commands, DaemonState, ExceptionAction, ProfileAction, RuleAction, SubdomainAction};
+
+pub const SOCKET_PATH: &str = "/run/focus.sock";
+const GROUP_NAME: &str = "focus";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Response {
+    ok: bool,
+    output: String,
+}
+
+/// Komutu çalışan daemon'a iletir. Daemon çalışmıyorsa None döner (doğrudan dosya düzenlemeye geçilir).
+pub fn send(command: &Commands) -> Option<Result<String>> {
+    let stream = match UnixStream::connect(SOCKET_PATH) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => return None,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            return Some(Err(e).context(format!("Daemon'a bağlanılamadı ({} grubunda değilsiniz, sudo kullanın)", GROUP_NAME)));
+        }
+        Err(e) => return Some(Err(e).context("Daemon'a bağlanılamadı")),
+    };
+    Some(request(stream, command))
+}
+
+/// Kontrol soketinde dinleyen bir daemon var mı?
+pub fn daemon_running() -> bool {
+    match UnixStream::connect(SOCKET_PATH) {
+        Ok(_) => true,
+        // Soket var ama bağlanma izni yoksa da daemon çalışıyordur
+        Err(e) => e.kind() == ErrorKind::PermissionDenied,
+    }
+}
+
+fn request(mut stream: UnixStream, command: &Commands) -> Result<String> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply).context("Daemon yanıt vermedi")?;
+    let response: Response = serde_json::from_str(&reply).context("Daemon yanıtı okunamadı")?;
+
+    if response.ok {
+        Ok(response.output)
+    } else {
+        anyhow::bail!(response.output)
+    }
+}
+
+/// Kontrol soketini açar ve gelen istekleri arka planda daemon durumuna uygular
+pub fn serve(state: Arc<Mutex<DaemonState>>) -> Result<()> {
+    if Path::new(SOCKET_PATH).exists() {
+        fs::remove_file(SOCKET_PATH).context("Eski soket silinemedi")?;
+    }
+    let listener = UnixListener::bind(SOCKET_PATH).context("Soket oluşturulamadı")?;
+    match group_id(GROUP_NAME) {
+        Some(gid) => {
+            std::os::unix::fs::chown(SOCKET_PATH, Some(0), Some(gid))?;
+            fs::set_permissions(SOCKET_PATH, fs::Permissions::from_mode(0o660))?;
+        }
+        None => fs::set_permissions(SOCKET_PATH, fs::Permissions::from_mode(0o600))?,
+    }
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handle_client(stream, &state) {
+                        eprintln!("Soket Hatası: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Soket Hatası: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_client(mut stream: UnixStream, state: &Mutex<DaemonState>) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    // daemon_running() yalnızca bağlanıp kapatır
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let result = serde_json::from_str::<Commands>(&line)
+        .context("Geçersiz istek")
+        .and_then(|command| {
+            authorize(&stream, &command)?;
+            state.lock().unwrap().handle(command)
+        });
+
+    let response = match result {
+        Ok(output) => Response { ok: true, output },
+        Err(e) => Response { ok: false, output: format!("{:#}", e) },
+    };
+
+    let mut reply = serde_json::to_string(&response)?;
+    reply.push('\n');
+    stream.write_all(reply.as_bytes())?;
+    Ok(())
+}
+
+/// İsteği gönderen kullanıcının bu işleme yetkisi var mı? root her şeyi yapabilir,
+/// focus grubu üyeleri yalnızca kuralları sıkılaştıran ve istisna hakkı harcayan işlemleri.
+fn authorize(stream: &UnixStream, command: &Commands) -> Result<()> {
+    let (uid, gid) = peer_credentials(stream)?;
+    if uid == 0 {
+        return Ok(());
+    }
+
+    let is_member = group_id(GROUP_NAME).is_some_and(|focus_gid| gid == focus_gid)
+        || user_name(uid).is_some_and(|name| group_members(GROUP_NAME).contains(&name));
+    if !is_member {
+        anyhow::bail!("Yetkisiz kullanıcı (uid {})", uid);
+    }
+
+    if !allowed_for_member(command) {
+        anyhow::bail!("Bu işlem için root yetkisi gerekli (sudo focus ...)");
+    }
+    Ok(())
+}
+
+fn allowed_for_member(command: &Commands) -> bool {
+    match command {
+        Commands::Add { .. } | Commands::Import { .. } | Commands::Session { .. } | Commands::Lock { .. } => true,
+        Commands::Exception { action } => matches!(action, ExceptionAction::Allow { .. }),
+        Commands::Subdomain { action } => !matches!(action, SubdomainAction::Remove { .. }),
+        Commands::Bw { action } => matches!(action, BwAction::On | BwAction::Rule { .. }),
+        Commands::Rule { action } => matches!(action, RuleAction::Enable { .. }),
+        Commands::Allowlist { action } => {
+            matches!(action, AllowlistAction::Add { .. } | AllowlistAction::Enable { .. } | AllowlistAction::Show)
+        }
+        Commands::Profile { action } => matches!(
+            action,
+            ProfileAction::Add { .. } | ProfileAction::Enable { .. } | ProfileAction::Show { .. }
+        ),
+        _ => false,
+    }
+}
+
+/// Soketin karşı ucundaki sürecin (uid, gid) bilgisi
+fn peer_credentials(stream: &UnixStream) -> Result<(u32, u32)> {
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred ve len geçerli, doğru boyutta yerel değişkenler
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error()).context("Kullanıcı kimliği alınamadı");
+    }
+    Ok((cred.uid, cred.gid))
+}
+
+/// /etc/group satırı: isim:x:gid:üye1,üye2
+fn group_entry(name: &str) -> Option<(u32, Vec<String>)> {
+    let content = fs::read_to_string("/etc/group").ok()?;
+    content.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() < 4 || fields[0] != name {
+            return None;
+        }
+        let members = fields[3].split(',').filter(|m| !m.is_empty()).map(str::to_string).collect();
+        Some((fields[2].parse().ok()?, members))
+    })
+}
+
+fn group_id(name: &str) -> Option<u32> {
+    group_entry(name).map(|(gid, _)| gid)
+}
+
+fn group_members(name: &str) -> Vec<String> {
+    group_entry(name).map(|(_, members)| members).unwrap_or_default()
+}
+
+/// /etc/passwd satırı: isim:x:uid:gid:...
+fn user_name(uid: u32) -> Option<String> {
+    let content = fs::read_to_string("/etc/passwd").ok()?;
+    content.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() > 2 && fields[2].parse() == Ok(uid)).then(|| fields[0].to_string())
+    })
+}
@@ -0,0 +1,472 @@
+// Yerel DNS sinkhole: engelli isimlere 0.0.0.0/:: (veya NXDOMAIN) döner, gerisini upstream'e iletir.
+// Sistemin bu adresi kullanması için resolv.conf / systemd-resolved ayarı gerekir.
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const RCODE_NXDOMAIN: u8 = 3;
+const RCODE_SERVFAIL: u8 = 2;
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+const BLOCKED_TTL: u32 = 60;
+// Sorguları karşılayan iş parçacığı ve eşzamanlı TCP bağlantısı sınırları
+const UDP_WORKERS: usize = 32;
+const MAX_TCP_CONNECTIONS: usize = 64;
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+// Toplu çözümlemede eşzamanlı sorgu ve toplam isim sınırı
+const LOOKUP_WORKERS: usize = 16;
+const MAX_LOOKUPS: usize = 2000;
+/// focus'un kendi sorgularının paket işareti (SO_MARK); wildcard DNS kuralları bu paketleri reddetmez
+pub const LOOKUP_MARK: u32 = 0x464f;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Sinkhole {
+    /// A/AAAA sorgularına 0.0.0.0 / :: döner
+    Zero,
+    /// Tüm sorgulara NXDOMAIN döner
+    Nxdomain,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DnsSettings {
+    pub listen: String,
+    pub upstream: String,
+    pub sinkhole: Sinkhole,
+}
+
+impl Default for DnsSettings {
+    fn default() -> Self {
+        Self {
+            listen: "127.0.0.153:53".to_string(),
+            upstream: "1.1.1.1:53".to_string(),
+            sinkhole: Sinkhole::Zero,
+        }
+    }
+}
+
+// İzin listesi modunda da her zaman çözülen isimler (yerel makine ve ters sorgular)
+const ALWAYS_ALLOWED: [&str; 2] = ["localhost", "arpa"];
+
+/// Engellenecek isimler: tam eşleşmeler ve tüm alt alan adlarıyla engellenen (wildcard) domainler.
+/// `allowed` verilmişse (izin listesi modu) bu domainler ve alt alan adları dışındaki her isim engellidir.
+#[derive(Debug, Default)]
+pub struct BlockList {
+    pub exact: HashSet<String>,
+    pub suffixes: Vec<String>,
+    pub allowed: Option<Vec<String>>,
+}
+
+/// İsim domainin kendisi veya bir alt alan adı mı?
+pub fn within(name: &str, domain: &str) -> bool {
+    name == domain || name.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.'))
+}
+
+impl BlockList {
+    pub fn is_blocked(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_lowercase();
+        if self.exact.contains(&name) || self.suffixes.iter().any(|s| within(&name, s)) {
+            return true;
+        }
+        match &self.allowed {
+            Some(allowed) => {
+                !allowed.iter().any(|d| within(&name, d)) && !ALWAYS_ALLOWED.iter().any(|d| within(&name, d))
+            }
+            None => false,
+        }
+    }
+}
+
+static BLOCKED: RwLock<Option<BlockList>> = RwLock::new(None);
+static SETTINGS: RwLock<Option<DnsSettings>> = RwLock::new(None);
+static STARTED: AtomicBool = AtomicBool::new(false);
+static TCP_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Resolver'ın kullandığı engel listesini ve ayarları günceller
+pub fn update(blocked: BlockList, settings: &DnsSettings) {
+    *BLOCKED.write().unwrap() = Some(blocked);
+    *SETTINGS.write().unwrap() = Some(settings.clone());
+}
+
+/// Resolver'ı (henüz çalışmıyorsa) başlatır. Dinleme adresi değişikliği daemon yeniden başlatılınca geçerli olur.
+/// UDP sorguları sabit sayıda iş parçacığıyla karşılanır; bekleyen sorgular soketin tamponunda kalır.
+/// Kesilmiş (TC) yanıt alan istemciler aynı adrese TCP ile tekrar sorar.
+pub fn ensure_started(settings: &DnsSettings) -> Result<()> {
+    if STARTED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    let socket = UdpSocket::bind(&settings.listen)
+        .with_context(|| format!("DNS soketi açılamadı: {}", settings.listen))?;
+    let listener = TcpListener::bind(&settings.listen)
+        .with_context(|| format!("DNS TCP soketi açılamadı: {}", settings.listen))?;
+    STARTED.store(true, Ordering::SeqCst);
+    println!("DNS sinkhole dinliyor: {}", settings.listen);
+
+    for _ in 0..UDP_WORKERS {
+        let socket = socket.try_clone().context("DNS soketi kopyalanamadı")?;
+        thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            loop {
+                let (len, client) = match socket.recv_from(&mut buf) {
+                    Ok(received) => received,
+                    Err(e) => {
+                        eprintln!("DNS Hatası: {}", e);
+                        continue;
+                    }
+                };
+                if let Some(reply) = handle_query(&buf[..len], Transport::Udp) {
+                    let _ = socket.send_to(&reply, client);
+                }
+            }
+        });
+    }
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            // Sınır aşıldıysa bağlantı kapatılır, istemci yeniden dener
+            if TCP_CONNECTIONS.fetch_add(1, Ordering::SeqCst) >= MAX_TCP_CONNECTIONS {
+                TCP_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            thread::spawn(move || {
+                let _ = serve_tcp(stream);
+                TCP_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Bir TCP bağlantısındaki (2 bayt uzunluk önekli) sorguları sırayla cevaplar
+fn serve_tcp(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TCP_IDLE_TIMEOUT))?;
+    stream.set_write_timeout(Some(TCP_IDLE_TIMEOUT))?;
+    loop {
+        let Ok(query) = read_message(&mut stream) else { return Ok(()) };
+        if let Some(reply) = handle_query(&query, Transport::Tcp) {
+            write_message(&mut stream, &reply)?;
+        }
+    }
+}
+
+fn read_message(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len)?;
+    let mut message = vec![0u8; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+fn write_message(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<()> {
+    let len = u16::try_from(message.len()).map_err(|_| std::io::ErrorKind::InvalidData)?;
+    let mut framed = len.to_be_bytes().to_vec();
+    framed.extend_from_slice(message);
+    stream.write_all(&framed)
+}
+
+/// İstemcinin sorduğu yol; upstream'e de aynı yolla iletilir
+#[derive(Clone, Copy)]
+enum Transport {
+    Udp,
+    Tcp,
+}
+
+fn handle_query(query: &[u8], transport: Transport) -> Option<Vec<u8>> {
+    let (name, qtype, question_end) = parse_question(query)?;
+
+    let blocked = BLOCKED
+        .read()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|list| list.is_blocked(&name));
+    let settings = SETTINGS.read().unwrap().clone().unwrap_or_default();
+
+    if blocked {
+        return Some(sinkhole_reply(query, question_end, qtype, settings.sinkhole));
+    }
+
+    let reply = match transport {
+        Transport::Udp => forward(query, &settings.upstream, false),
+        Transport::Tcp => forward_tcp(query, &settings.upstream),
+    };
+    match reply {
+        Ok(reply) => Some(reply),
+        Err(e) => {
+            eprintln!("DNS upstream hatası ({}): {}", settings.upstream, e);
+            Some(error_reply(query, question_end, RCODE_SERVFAIL))
+        }
+    }
+}
+
+/// Sorguyu upstream'e iletir. `marked` ise soket LOOKUP_MARK ile işaretlenir; istemcilerden gelen
+/// sorgular işaretlenmez, yoksa wildcard kurallarını aşarlardı.
+fn forward(query: &[u8], upstream: &str, marked: bool) -> Result<Vec<u8>> {
+    let upstream: SocketAddr = upstream.parse().context("Upstream adresi hatalı")?;
+    let bind = if upstream.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = UdpSocket::bind(bind)?;
+    if marked {
+        set_mark(&socket, LOOKUP_MARK)?;
+    }
+    socket.set_read_timeout(Some(UPSTREAM_TIMEOUT))?;
+    socket.send_to(query, upstream)?;
+
+    let mut buf = [0u8; 4096];
+    let (len, _) = socket.recv_from(&mut buf)?;
+    Ok(buf[..len].to_vec())
+}
+
+/// Sorguyu upstream'e TCP ile iletir (kesilmiş UDP yanıtı alan istemciler için)
+fn forward_tcp(query: &[u8], upstream: &str) -> Result<Vec<u8>> {
+    let upstream: SocketAddr = upstream.parse().context("Upstream adresi hatalı")?;
+    let mut stream = TcpStream::connect_timeout(&upstream, UPSTREAM_TIMEOUT)?;
+    stream.set_read_timeout(Some(UPSTREAM_TIMEOUT))?;
+    stream.set_write_timeout(Some(UPSTREAM_TIMEOUT))?;
+    write_message(&mut stream, query)?;
+    Ok(read_message(&mut stream)?)
+}
+
+/// Sorgudaki ilk soruyu okur: (isim, tip, sorunun bittiği offset)
+fn parse_question(packet: &[u8]) -> Option<(String, u16, usize)> {
+    if packet.len() < 12 || u16::from_be_bytes([packet[4], packet[5]]) == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut pos = 12;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // Soru kısmında sıkıştırma (pointer) beklenmez
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        let label = packet.get(pos..pos + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += len;
+    }
+
+    let qtype = u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]);
+    // qtype + qclass
+    let question_end = pos + 4;
+    if packet.len() < question_end {
+        return None;
+    }
+    Some((labels.join("."), qtype, question_end))
+}
+
+/// Sorgu başlığı ve sorusu kopyalanmış, cevapsız bir yanıt iskeleti
+fn reply_header(query: &[u8], question_end: usize, rcode: u8, answers: u16) -> Vec<u8> {
+    let mut reply = query[..question_end].to_vec();
+    // QR=1, opcode ve RD korunur
+    reply[2] = 0x80 | (query[2] & 0x79);
+    // RA=1
+    reply[3] = 0x80 | rcode;
+    reply[4..6].copy_from_slice(&1u16.to_be_bytes());
+    reply[6..8].copy_from_slice(&answers.to_be_bytes());
+    reply[8..12].fill(0);
+    reply
+}
+
+fn error_reply(query: &[u8], question_end: usize, rcode: u8) -> Vec<u8> {
+    reply_header(query, question_end, rcode, 0)
+}
+
+fn sinkhole_reply(query: &[u8], question_end: usize, qtype: u16, sinkhole: Sinkhole) -> Vec<u8> {
+    let address: &[u8] = match (sinkhole, qtype) {
+        (Sinkhole::Zero, TYPE_A) => &[0; 4],
+        (Sinkhole::Zero, TYPE_AAAA) => &[0; 16],
+        (Sinkhole::Zero, _) => return reply_header(query, question_end, 0, 0),
+        (Sinkhole::Nxdomain, _) => return error_reply(query, question_end, RCODE_NXDOMAIN),
+    };
+
+    let mut reply = reply_header(query, question_end, 0, 1);
+    // Sorudaki isme pointer (offset 12)
+    reply.extend_from_slice(&[0xC0, 0x0C]);
+    reply.extend_from_slice(&qtype.to_be_bytes());
+    reply.extend_from_slice(&1u16.to_be_bytes());
+    reply.extend_from_slice(&BLOCKED_TTL.to_be_bytes());
+    reply.extend_from_slice(&(address.len() as u16).to_be_bytes());
+    reply.extend_from_slice(address);
+    reply
+}
+
+/// İsmi doğrudan upstream sunucuya sorarak A ve AAAA kayıtlarını döner
+/// (hosts backend'i aktifken sistem çözücüsü 127.0.0.1 döneceği için kullanılamaz)
+pub fn lookup(name: &str, upstream: &str) -> Result<Vec<IpAddr>> {
+    let mut addresses = Vec::new();
+    for (id, qtype) in [(0x4631, TYPE_A), (0x4632, TYPE_AAAA)] {
+        let reply = forward(&build_query(id, name, qtype), upstream, true)?;
+        addresses.extend(parse_addresses(&reply).unwrap_or_default());
+    }
+    Ok(addresses)
+}
+
+/// İsimleri LOOKUP_WORKERS iş parçacığıyla paralel çözer; her isim ayrı ayrı zaman aşımına
+/// uğrayabildiği için sıralı çözüm binlerce isimde dakikalar sürer. En fazla MAX_LOOKUPS isim çözülür.
+pub fn lookup_all(names: &[String], upstream: &str) -> BTreeSet<IpAddr> {
+    if names.len() > MAX_LOOKUPS {
+        eprintln!("{} isimden yalnızca ilk {} tanesi çözülüyor", names.len(), MAX_LOOKUPS);
+    }
+    let names = &names[..names.len().min(MAX_LOOKUPS)];
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+
+    let mut addresses = BTreeSet::new();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..LOOKUP_WORKERS.min(names.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut found = Vec::new();
+                    while let Some(name) = names.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        match lookup(name, upstream) {
+                            Ok(addresses) => found.extend(addresses),
+                            Err(_) => {
+                                failed.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                    found
+                })
+            })
+            .collect();
+        for worker in workers {
+            addresses.extend(worker.join().unwrap_or_default());
+        }
+    });
+
+    let failed = failed.into_inner();
+    if failed > 0 {
+        eprintln!("{} isim çözülemedi (upstream: {})", failed, upstream);
+    }
+    addresses
+}
+
+fn set_mark(socket: &UdpSocket, mark: u32) -> Result<()> {
+    // SAFETY: geçerli bir soket ve boyutu doğru verilen u32 değer
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_MARK,
+            (&mark as *const u32).cast(),
+            size_of::<u32>() as libc::socklen_t,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error()).context("Sorgu soketi işaretlenemedi (SO_MARK)");
+    }
+    Ok(())
+}
+
+fn build_query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+    let mut query = Vec::new();
+    query.extend_from_slice(&id.to_be_bytes());
+    // RD=1, tek soru
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes());
+    query
+}
+
+/// Paketteki (sıkıştırılmış olabilecek) ismin bittiği offset
+fn skip_name(packet: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(pos)?;
+        if len & 0xC0 == 0xC0 {
+            return Some(pos + 2);
+        }
+        pos += 1;
+        if len == 0 {
+            return Some(pos);
+        }
+        pos += len as usize;
+    }
+}
+
+fn parse_addresses(reply: &[u8]) -> Option<Vec<IpAddr>> {
+    let (_, _, mut pos) = parse_question(reply)?;
+    let answers = u16::from_be_bytes([reply[6], reply[7]]);
+    let mut addresses = Vec::new();
+
+    for _ in 0..answers {
+        pos = skip_name(reply, pos)?;
+        let header = reply.get(pos..pos + 10)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let rdlength = u16::from_be_bytes([header[8], header[9]]) as usize;
+        pos += 10;
+        let rdata = reply.get(pos..pos + rdlength)?;
+        pos += rdlength;
+
+        match (rtype, rdlength) {
+            (TYPE_A, 4) => {
+                let octets: [u8; 4] = rdata.try_into().ok()?;
+                addresses.push(IpAddr::from(octets));
+            }
+            (TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = rdata.try_into().ok()?;
+                addresses.push(IpAddr::from(octets));
+            }
+            // CNAME vb. atlanır, zincirin sonundaki A/AAAA kayıtları aynı yanıtta gelir
+            _ => {}
+        }
+    }
+    Some(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(exact: &[&str], suffixes: &[&str], allowed: Option<&[&str]>) -> BlockList {
+        let strings = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        BlockList {
+            exact: strings(exact).into_iter().collect(),
+            suffixes: strings(suffixes),
+            allowed: allowed.map(strings),
+        }
+    }
+
+    #[test]
+    fn blocks_exact_names_and_wildcard_subdomains() {
+        let list = list(&["youtube.com", "www.youtube.com"], &["example.org"], None);
+        assert!(list.is_blocked("youtube.com"));
+        assert!(list.is_blocked("WWW.YouTube.com."));
+        assert!(!list.is_blocked("m.youtube.com"));
+        assert!(list.is_blocked("example.org"));
+        assert!(list.is_blocked("a.b.example.org"));
+        assert!(!list.is_blocked("notexample.org"));
+        assert!(!list.is_blocked("example.org.evil.com"));
+    }
+
+    #[test]
+    fn allowlist_blocks_everything_else() {
+        let list = list(&["docs.rs"], &[], Some(&["rust-lang.org", "docs.rs"]));
+        assert!(!list.is_blocked("rust-lang.org"));
+        assert!(!list.is_blocked("doc.rust-lang.org"));
+        assert!(!list.is_blocked("localhost"));
+        assert!(!list.is_blocked("1.0.0.127.in-addr.arpa"));
+        assert!(list.is_blocked("youtube.com"));
+        // Engel kuralı izin listesinden önce gelir
+        assert!(list.is_blocked("docs.rs"));
+    }
+}
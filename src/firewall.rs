@@ -0,0 +1,438 @@
+// Güvenlik duvarı işlemleri: focus'a ait nftables tablosu, wildcard DNS kuralları
+// ve yeni engellenen sitelere açık bağlantıların kesilmesi.
+// Yapılan her değişiklik /run/focus altındaki günlüğe yazılır; daemon çökse bile bir sonraki
+// başlangıçta (veya focus repair ile) tam olarak eklenenler geri alınır.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::dns;
+
+// /run tmpfs'tir: yeniden başlatmada güvenlik duvarı kurallarıyla birlikte silinir
+const JOURNAL_PATH: &str = "/run/focus/firewall.json";
+
+// iptables'ta focus'a ait zincir; sistem zincirlerine yalnızca bu zincire tek bir atlama eklenir
+const IPT_CHAIN: &str = "FOCUS";
+const IPT_PARENT: &str = "OUTPUT";
+const NFT_TABLE: &str = "focus";
+// Tarayıcıların DNS önbelleği dolana kadar yeni engellenen adreslere bağlantı reddedilir
+const CUTOFF_DURATION: Duration = Duration::from_secs(45);
+// Zincire yazılan wildcard domain sınırı (domain başına protokol ve aile başına bir string eşleştirme kuralı)
+const MAX_WILDCARD_DOMAINS: usize = 500;
+
+/// Güvenlik duvarına yapılan tek bir değişiklik
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Change {
+    /// focus'a ait nftables tablosu (içindeki setler ve kurallarla birlikte)
+    NftTable { name: String },
+    /// iptables/ip6tables'ta focus'a ait zincir ve ona atlayan kural
+    IptablesChain { program: String, chain: String, parent: String },
+    /// Süreli bağlantı kesme; süre dolunca nft kendisi siler
+    Cutoff { addresses: Vec<IpAddr> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    change: Change,
+    added_at: DateTime<Local>,
+    expires_at: Option<DateTime<Local>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Journal {
+    entries: Vec<Entry>,
+}
+
+impl Journal {
+    fn load() -> Self {
+        fs::read_to_string(JOURNAL_PATH)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = Path::new(JOURNAL_PATH).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Yarım yazılmış günlük kalmasın diye önce geçici dosyaya yazılır
+        let tmp = format!("{}.tmp", JOURNAL_PATH);
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, JOURNAL_PATH)?;
+        Ok(())
+    }
+
+    /// Değişikliği günlüğe ekler; aynısı zaten kayıtlıysa tekrar eklemez
+    fn record(change: Change, expires_at: Option<DateTime<Local>>) {
+        let mut journal = Self::load();
+        let now = Local::now();
+        journal.entries.retain(|e| e.expires_at.is_none_or(|t| t > now));
+        if expires_at.is_none() && journal.entries.iter().any(|e| e.change == change) {
+            return;
+        }
+        journal.entries.push(Entry { change, added_at: now, expires_at });
+        if let Err(e) = journal.save() {
+            eprintln!("Güvenlik duvarı günlüğü yazılamadı: {}", e);
+        }
+    }
+
+    fn forget(change: &Change) {
+        let mut journal = Self::load();
+        journal.entries.retain(|e| e.change != *change);
+        let _ = journal.save();
+    }
+}
+
+/// Günlükteki bir değişikliği geri alır
+fn undo(change: &Change) {
+    match change {
+        Change::NftTable { name } => {
+            let _ = run_nft(&format!("table inet {t} {{}}\ndelete table inet {t}\n", t = name));
+        }
+        Change::IptablesChain { program, chain, parent } => {
+            let _ = Command::new(program).args(["-w", "-D", parent, "-j", chain]).output();
+            let _ = Command::new(program).args(["-w", "-F", chain]).output();
+            let _ = Command::new(program).args(["-w", "-X", chain]).output();
+        }
+        // Setler tablo ile birlikte silinir, süresi dolanları nft zaten kaldırmıştır
+        Change::Cutoff { .. } => {}
+    }
+}
+
+/// Günlükteki tüm değişiklikleri (tablolar en sona kalacak şekilde) geri alır ve günlüğü boşaltır.
+/// Geri alınan değişiklik sayısını döner.
+pub fn undo_journal() -> usize {
+    let journal = Journal::load();
+    let mut entries = journal.entries.clone();
+    entries.sort_by_key(|e| matches!(e.change, Change::NftTable { .. }));
+    for entry in entries.iter().rev().filter(|e| !matches!(e.change, Change::NftTable { .. })) {
+        undo(&entry.change);
+    }
+    for entry in entries.iter().filter(|e| matches!(e.change, Change::NftTable { .. })) {
+        undo(&entry.change);
+    }
+    let _ = Journal::default().save();
+    entries.len()
+}
+
+/// Günlükteki kayıtların okunabilir listesi
+pub fn describe_journal() -> Vec<String> {
+    Journal::load()
+        .entries
+        .iter()
+        .map(|e| {
+            let what = match &e.change {
+                Change::NftTable { name } => format!("nft tablosu: inet {}", name),
+                Change::IptablesChain { program, chain, parent } => format!("{} zinciri: {} ({} -> {})", program, chain, parent, chain),
+                Change::Cutoff { addresses } => format!("bağlantı kesme: {} adres", addresses.len()),
+            };
+            match e.expires_at {
+                Some(t) => format!("{} (bitiş {})", what, t.format("%H:%M:%S")),
+                None => what,
+            }
+        })
+        .collect()
+}
+
+/// DNS paketindeki isim biçimi: |07|example|03|com|00|
+fn dns_hex_pattern(domain: &str) -> String {
+    let mut pattern = String::new();
+    for label in domain.split('.') {
+        pattern.push_str(&format!("|{:02x}|{}", label.len(), label));
+    }
+    pattern.push_str("|00|");
+    pattern
+}
+
+/// focus zincirini (yoksa) oluşturur ve üst zincire (yoksa) tek bir atlama ekler
+fn ensure_chain(program: &str) -> Result<()> {
+    // Günlük önce yazılır, yarıda kalırsa da temizlenebilsin
+    Journal::record(
+        Change::IptablesChain {
+            program: program.to_string(),
+            chain: IPT_CHAIN.to_string(),
+            parent: IPT_PARENT.to_string(),
+        },
+        None,
+    );
+
+    let exists = |args: &[&str]| {
+        Command::new(program).arg("-w").args(args).output().is_ok_and(|o| o.status.success())
+    };
+    if !exists(&["-n", "-L", IPT_CHAIN]) && !exists(&["-N", IPT_CHAIN]) {
+        anyhow::bail!("{} zinciri oluşturulamadı ({})", IPT_CHAIN, program);
+    }
+    if !exists(&["-C", IPT_PARENT, "-j", IPT_CHAIN]) && !exists(&["-I", IPT_PARENT, "1", "-j", IPT_CHAIN]) {
+        anyhow::bail!("{} zincirine atlama eklenemedi ({})", IPT_CHAIN, program);
+    }
+    Ok(())
+}
+
+/// Wildcard domainler ve tüm alt alan adları için DNS sorgularını güvenlik duvarında reddeder.
+/// Kurallar focus zincirinde tutulur; zincir her seferinde tek bir iptables-restore çağrısıyla
+/// boşaltılıp yeniden doldurulur, böylece değişiklik sırasında engelde boşluk kalmaz.
+/// Her DNS paketi tüm kurallarla karşılaştırıldığı için en fazla MAX_WILDCARD_DOMAINS domain yazılır.
+pub fn sync_wildcard_rules(wildcards: &[String]) {
+    if wildcards.len() > MAX_WILDCARD_DOMAINS {
+        eprintln!(
+            "{} wildcard domainden yalnızca ilk {} tanesi güvenlik duvarına yazıldı; tüm alt alan adları için dns backend'ini kullanın",
+            wildcards.len(),
+            MAX_WILDCARD_DOMAINS
+        );
+    }
+    let wildcards = &wildcards[..wildcards.len().min(MAX_WILDCARD_DOMAINS)];
+
+    let journal = Journal::load();
+    for program in ["iptables", "ip6tables"] {
+        let chain = journal.entries.iter().find_map(|e| match &e.change {
+            change @ Change::IptablesChain { program: p, .. } if p == program => Some(change.clone()),
+            _ => None,
+        });
+        if wildcards.is_empty() {
+            // Wildcard kalmadıysa zincir tamamen kaldırılır
+            if let Some(change) = chain {
+                undo(&change);
+                Journal::forget(&change);
+            }
+            continue;
+        }
+        if let Err(e) = ensure_chain(program) {
+            eprintln!("{:#}", e);
+            continue;
+        }
+
+        let mut script = format!("*filter\n-F {}\n", IPT_CHAIN);
+        // Daemon'un kendi çözümlemeleri (bağlantı kesme, nftables) engellenmemeli
+        script.push_str(&format!("-A {} -m mark --mark {} -j RETURN\n", IPT_CHAIN, dns::LOOKUP_MARK));
+        for domain in wildcards {
+            let pattern = dns_hex_pattern(domain);
+            for proto in ["udp", "tcp"] {
+                script.push_str(&format!(
+                    "-A {} -p {} --dport 53 -m string --algo bm --icase --hex-string \"{}\" -j REJECT\n",
+                    IPT_CHAIN, proto, pattern
+                ));
+            }
+        }
+        script.push_str("COMMIT\n");
+        if let Err(e) = run_restore(program, &script) {
+            eprintln!("Wildcard kuralları yüklenemedi: {:#}", e);
+        }
+    }
+}
+
+/// Kuralları tek seferde ve atomik olarak yükler; diğer zincirlere dokunulmaz (--noflush)
+fn run_restore(program: &str, script: &str) -> Result<()> {
+    let restore = format!("{}-restore", program);
+    let mut child = Command::new(&restore)
+        .args(["-w", "--noflush"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("{} çalıştırılamadı", restore))?;
+    child.stdin.take().context("stdin")?.write_all(script.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("{}: {}", restore, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn run_nft(script: &str) -> Result<()> {
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("nft çalıştırılamadı")?;
+    child.stdin.take().context("nft stdin")?.write_all(script.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("nft: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// focus tablosunu (yoksa) oluşturur ve kurallarını tazeler.
+/// blocked*: engel süresince kalıcı, cutoff*: yeni engellenenler için süreli (timeout) setler.
+fn table_script() -> String {
+    let mut script = format!(
+        "table inet {t} {{\n\
+         \tset blocked4 {{ type ipv4_addr; }}\n\
+         \tset blocked6 {{ type ipv6_addr; }}\n\
+         \tset cutoff4 {{ type ipv4_addr; flags timeout; }}\n\
+         \tset cutoff6 {{ type ipv6_addr; flags timeout; }}\n\
+         \tchain output {{ type filter hook output priority 0; policy accept; }}\n\
+         }}\n\
+         flush chain inet {t} output\n",
+        t = NFT_TABLE
+    );
+    for (family, v) in [("ip", "4"), ("ip6", "6")] {
+        for set in ["blocked", "cutoff"] {
+            script.push_str(&format!(
+                "add rule inet {t} output {f} daddr @{s}{v} meta l4proto tcp reject with tcp reset\n\
+                 add rule inet {t} output {f} daddr @{s}{v} reject\n",
+                t = NFT_TABLE, f = family, s = set, v = v
+            ));
+        }
+    }
+    script
+}
+
+fn elements(addresses: &BTreeSet<IpAddr>, v4: bool, timeout: Option<Duration>) -> String {
+    addresses
+        .iter()
+        .filter(|a| a.is_ipv4() == v4)
+        .map(|a| match timeout {
+            Some(t) => format!("{} timeout {}s", a, t.as_secs()),
+            None => a.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Tabloyu günlüğe işleyip betiği çalıştırır (günlük önce yazılır, çökmede de temizlenebilsin)
+fn run_table_script(script: &str) -> Result<()> {
+    Journal::record(Change::NftTable { name: NFT_TABLE.to_string() }, None);
+    run_nft(script)
+}
+
+/// Kalıcı engel setlerini verilen adreslerle değiştirir (tek nft çağrısı, atomik)
+pub fn set_blocked_addresses(addresses: &BTreeSet<IpAddr>) -> Result<()> {
+    let mut script = table_script();
+    for (set, v4) in [("blocked4", true), ("blocked6", false)] {
+        script.push_str(&format!("flush set inet {} {}\n", NFT_TABLE, set));
+        let list = elements(addresses, v4, None);
+        if !list.is_empty() {
+            script.push_str(&format!("add element inet {} {} {{ {} }}\n", NFT_TABLE, set, list));
+        }
+    }
+    run_table_script(&script)
+}
+
+/// Adreslere yeni bağlantıları CUTOFF_DURATION boyunca reddeder; süre dolunca nft kendisi siler
+fn add_cutoff(addresses: &BTreeSet<IpAddr>) -> Result<()> {
+    let mut script = table_script();
+    for (set, v4) in [("cutoff4", true), ("cutoff6", false)] {
+        let list = elements(addresses, v4, Some(CUTOFF_DURATION));
+        if !list.is_empty() {
+            script.push_str(&format!("add element inet {} {} {{ {} }}\n", NFT_TABLE, set, list));
+        }
+    }
+    let expires_at = Local::now() + chrono::Duration::from_std(CUTOFF_DURATION)?;
+    Journal::record(Change::Cutoff { addresses: addresses.iter().copied().collect() }, Some(expires_at));
+    run_table_script(&script)
+}
+
+/// Yalnızca verilen adreslere ait soketleri ve conntrack kayıtlarını siler
+fn kill_connections_to(addresses: &BTreeSet<IpAddr>) {
+    for address in addresses {
+        let ip = address.to_string();
+        let family = if address.is_ipv4() { "ipv4" } else { "ipv6" };
+        let _ = Command::new("ss").args(["-K", "dst", &ip]).output();
+        let _ = Command::new("conntrack").args(["-D", "-f", family, "-d", &ip]).output();
+    }
+}
+
+/// Yeni engellenen isimlere açık bağlantıları keser. Diğer tüm bağlantılar (görüntülü görüşme,
+/// git push vb.) etkilenmez.
+pub fn cut_connections(names: &[String], upstream: &str) {
+    let addresses = dns::lookup_all(names, upstream);
+    if addresses.is_empty() {
+        return;
+    }
+
+    if let Err(e) = add_cutoff(&addresses) {
+        eprintln!("Bağlantı kesme kuralı eklenemedi: {:#}", e);
+    }
+    kill_connections_to(&addresses);
+
+    // Tarayıcılar önbellekteki IP ile yeniden bağlanmaya çalışır; süre boyunca temizlemeye devam et
+    thread::spawn(move || {
+        let start = Instant::now();
+        while start.elapsed() < CUTOFF_DURATION {
+            thread::sleep(Duration::from_secs(2));
+            kill_connections_to(&addresses);
+        }
+    });
+}
+
+/// Şu an kurulu olan focus kuralları (günlük + iptables/nft'den canlı liste)
+pub fn status() -> String {
+    let mut out = String::new();
+
+    let journal = describe_journal();
+    out.push_str("Günlük:\n");
+    if journal.is_empty() {
+        out.push_str("  (kayıt yok)\n");
+    }
+    for line in journal {
+        out.push_str(&format!("  {}\n", line));
+    }
+
+    for program in ["iptables", "ip6tables"] {
+        if let Ok(output) = Command::new(program).args(["-w", "-S", IPT_CHAIN]).output()
+            && output.status.success()
+        {
+            out.push_str(&format!("{} {}:\n", program, IPT_CHAIN));
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                out.push_str(&format!("  {}\n", line));
+            }
+        }
+    }
+
+    if let Ok(output) = Command::new("nft").args(["list", "table", "inet", NFT_TABLE]).output()
+        && output.status.success()
+    {
+        out.push_str(&format!("nft inet {}:\n", NFT_TABLE));
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            out.push_str(&format!("  {}\n", line));
+        }
+    }
+    out
+}
+
+/// focus'un eklediği her şeyi kaldırır: günlükteki değişiklikler ve eski sürümlerin
+/// eklediği port bazlı REJECT kuralları. Geri alınan değişiklik sayısını döner.
+pub fn cleanup() -> usize {
+    let undone = undo_journal();
+
+    let commands = [
+        "iptables -w -D OUTPUT -p tcp --dport 443 -j REJECT --reject-with tcp-reset",
+        "iptables -w -D OUTPUT -p udp --dport 443 -j REJECT --reject-with icmp-port-unreachable",
+        "iptables -w -D OUTPUT -p tcp --dport 80 -j REJECT --reject-with tcp-reset",
+
+        "iptables -w -D INPUT -p tcp --sport 443 -j REJECT --reject-with tcp-reset",
+        "iptables -w -D INPUT -p udp --sport 443 -j REJECT --reject-with icmp-port-unreachable",
+        "iptables -w -D INPUT -p tcp --sport 80 -j REJECT --reject-with tcp-reset",
+
+        "ip6tables -w -D OUTPUT -p tcp --dport 443 -j REJECT --reject-with tcp-reset",
+        "ip6tables -w -D OUTPUT -p udp --dport 443 -j REJECT --reject-with icmp6-port-unreachable",
+        "ip6tables -w -D OUTPUT -p tcp --dport 80 -j REJECT --reject-with tcp-reset",
+        
+        "ip6tables -w -D INPUT -p tcp --sport 443 -j REJECT --reject-with tcp-reset",
+        "ip6tables -w -D INPUT -p udp --sport 443 -j REJECT --reject-with icmp6-port-unreachable",
+        "ip6tables -w -D INPUT -p tcp --sport 80 -j REJECT --reject-with tcp-reset",
+    ];
+
+    for cmd in commands {
+        let parts: Vec<&str> = cmd.split_whitespace().collect();
+        if let Some(program) = parts.first() {
+            let args = &parts[1..];
+            let _ = Command::new(program).args(args).output();
+        }
+    }
+
+    undone
+}
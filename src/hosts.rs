@@ -0,0 +1,287 @@
+// /etc/hosts işlemleri: FOCUS bloğunun oluşturulması, doğrulanması ve güvenli yazılması.
+// Dosya önce geçici dosyaya yazılıp fsync edilir, sonra rename ile değiştirilir; yarıda kalan
+// bir yazma asla kısaltılmış bir hosts dosyası bırakmaz. Her değişiklikten önce yedek alınır.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+use crate::domain;
+
+pub const MARKER_START: &str = "# BEGIN FOCUS BLOCK";
+pub const MARKER_END: &str = "# END FOCUS BLOCK";
+pub const BACKUP_DIR: &str = "/var/lib/focus/hosts";
+const BLOCK_ADDRESS: &str = "127.0.0.1";
+// hosts.1 en yeni yedektir
+const BACKUP_COUNT: usize = 5;
+
+/// FOCUS bloğu çıkarılmış içerik (kullanıcıya ait kısım)
+pub fn strip_block(content: &str) -> String {
+    render(content, &[])
+}
+
+/// Mevcut hosts içeriğinden FOCUS bloğunu çıkarıp verilen isimlerle yenisini dosyanın sonuna ekler.
+/// Geçerli bir ad olmayan isimler (boşluk veya satır sonu içerenler dahil) yazılmaz.
+/// Blok dışındaki satırlar satır sonlarıyla birlikte olduğu gibi korunur.
+pub fn render(content: &str, names: &[String]) -> String {
+    let mut rendered = outside_block(content);
+
+    let names: Vec<&String> = names.iter().filter(|name| domain::is_hostname(name)).collect();
+    if !names.is_empty() {
+        if !rendered.is_empty() && !rendered.ends_with('\n') {
+            rendered.push('\n');
+        }
+        rendered.push_str(MARKER_START);
+        rendered.push('\n');
+        for name in names {
+            rendered.push_str(&format!("{} {}\n", BLOCK_ADDRESS, name));
+        }
+        rendered.push_str(MARKER_END);
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// FOCUS bloğu dışındaki satırlar, bayt bayt aynı
+fn outside_block(content: &str) -> String {
+    let mut outside = String::new();
+    let mut in_block = false;
+    for line in content.split_inclusive('\n') {
+        match line.trim() {
+            MARKER_START => in_block = true,
+            MARKER_END => in_block = false,
+            _ if !in_block => outside.push_str(line),
+            _ => {}
+        }
+    }
+    outside
+}
+
+/// İçeriğin geçerli bir hosts dosyası olup olmadığını kontrol eder: her satır boş, yorum
+/// veya "IP isim..." biçiminde olmalı, FOCUS bloğu en fazla bir kez bulunmalı ve kapanmalı.
+/// Bloktaki her satır tam olarak "127.0.0.1 geçerli-ad" olmalı.
+pub fn validate(content: &str) -> Result<()> {
+    check(content, true)
+}
+
+/// `user_lines` false ise yalnızca FOCUS bloğu denetlenir; blok dışındaki satırlar kullanıcınındır
+fn check(content: &str, user_lines: bool) -> Result<()> {
+    let mut blocks = 0;
+    let mut in_block = false;
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line == MARKER_START {
+            anyhow::ensure!(!in_block && blocks == 0, "{}. satır: birden fazla FOCUS bloğu", number + 1);
+            in_block = true;
+            blocks += 1;
+            continue;
+        }
+        if line == MARKER_END {
+            anyhow::ensure!(in_block, "{}. satır: açılmamış FOCUS bloğu kapatılıyor", number + 1);
+            in_block = false;
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if in_block {
+            let entry = line.strip_prefix(BLOCK_ADDRESS).and_then(|rest| rest.strip_prefix(' '));
+            anyhow::ensure!(
+                entry.is_some_and(domain::is_hostname),
+                "{}. satır: FOCUS bloğunda geçersiz satır '{}'",
+                number + 1,
+                line
+            );
+            continue;
+        }
+        if !user_lines {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let address = fields.next().unwrap_or_default();
+        // IPv6 bağlantı-yerel adresler arayüz içerebilir (fe80::1%lo0)
+        let ip = address.split_once('%').map_or(address, |(ip, _)| ip);
+        anyhow::ensure!(ip.parse::<IpAddr>().is_ok(), "{}. satır: geçersiz adres '{}'", number + 1, address);
+        anyhow::ensure!(fields.next().is_some(), "{}. satır: isim yok", number + 1);
+    }
+    anyhow::ensure!(!in_block, "FOCUS bloğu kapatılmamış");
+    Ok(())
+}
+
+/// Dosyadaki FOCUS bloğu verilen isimlerle birebir aynı mı?
+pub fn block_intact(path: &Path, names: &[String]) -> bool {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    render(&content, names) == content
+}
+
+/// Yazmadan önceki son kontrol: blok dışındaki içerik bayt bayt aynı kalmalı (son satıra eklenen
+/// satır sonu hariç), FOCUS bloğu geçerli olmalı. Kullanıcının kendi satırları denetlenmez;
+/// sistemin kabul ettiği ama bizim tanımadığımız bir satır güncellemeleri engellememeli.
+fn check_rendered(original: &str, rendered: &str) -> Result<()> {
+    check(rendered, false).context("Oluşturulan hosts içeriği geçersiz")?;
+    let (before, after) = (outside_block(original), outside_block(rendered));
+    anyhow::ensure!(
+        after == before || (!before.ends_with('\n') && after.strip_suffix('\n') == Some(before.as_str())),
+        "Oluşturulan hosts içeriği FOCUS bloğu dışındaki satırları değiştiriyor"
+    );
+    Ok(())
+}
+
+/// Hosts dosyasındaki FOCUS bloğunu verilen isimlerle günceller. Dosya değiştiyse true döner.
+pub fn update(path: &Path, names: &[String]) -> Result<bool> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let rendered = render(&content, names);
+    if rendered == content {
+        return Ok(false);
+    }
+    check_rendered(&content, &rendered)?;
+
+    // Yedekler ve önbellek temizliği yalnızca sistemin hosts dosyası içindir
+    let system = path == Path::new(crate::HOSTS_PATH);
+    if system && let Err(e) = backup(&content) {
+        eprintln!("Hosts yedeği alınamadı: {:#}", e);
+    }
+    write_atomic(path, &rendered).context("Hosts dosyası yazılamadı (sudo?)")?;
+    if system {
+        flush_caches();
+    }
+    Ok(true)
+}
+
+/// Geçici dosyaya yazıp fsync eder, izinleri ve sahipliği koruyarak asıl dosyanın yerine taşır
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let file_name = path.file_name().context("Geçersiz dosya yolu")?.to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.focus-tmp", file_name));
+    let metadata = fs::metadata(path).ok();
+
+    let result = (|| -> Result<()> {
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Some(metadata) = &metadata {
+            fs::set_permissions(&tmp, metadata.permissions())?;
+            std::os::unix::fs::chown(&tmp, Some(metadata.uid()), Some(metadata.gid()))?;
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    // rename'in kalıcı olması için dizin de fsync edilir
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+fn backup_path(index: usize) -> PathBuf {
+    Path::new(BACKUP_DIR).join(format!("hosts.{}", index))
+}
+
+/// Geçerli içeriği yedekler; en yeni yedekle aynıysa yeni yedek açılmaz
+fn backup(content: &str) -> Result<()> {
+    // Bozuk veya boş bir dosya "son sağlam hal" olarak saklanmaz
+    if content.trim().is_empty() || validate(content).is_err() {
+        return Ok(());
+    }
+    if fs::read_to_string(backup_path(1)).is_ok_and(|latest| latest == content) {
+        return Ok(());
+    }
+
+    fs::create_dir_all(BACKUP_DIR).with_context(|| format!("{} oluşturulamadı", BACKUP_DIR))?;
+    for index in (1..BACKUP_COUNT).rev() {
+        let from = backup_path(index);
+        if from.exists() {
+            fs::rename(&from, backup_path(index + 1))?;
+        }
+    }
+    write_atomic(&backup_path(1), content)
+}
+
+/// FOCUS bloğunu kaldırır ve dosyayı son sağlam yedeğe döndürür.
+/// Yedek yoksa yalnızca mevcut dosyadaki blok silinir.
+pub fn restore(path: &Path) -> Result<String> {
+    let current = fs::read_to_string(path).unwrap_or_default();
+    let latest = (1..=BACKUP_COUNT)
+        .map(backup_path)
+        .find_map(|p| fs::read_to_string(&p).ok().filter(|c| validate(c).is_ok()).map(|c| (p, c)));
+
+    let (source, content) = match latest {
+        Some((backup, content)) => (backup.display().to_string(), content),
+        None => {
+            validate(&current).context("Yedek yok ve mevcut hosts dosyası geçersiz")?;
+            ("mevcut dosya".to_string(), current.clone())
+        }
+    };
+
+    let restored = strip_block(&content);
+    if restored != current {
+        if let Err(e) = backup(&current) {
+            eprintln!("Hosts yedeği alınamadı: {:#}", e);
+        }
+        write_atomic(path, &restored).context("Hosts dosyası yazılamadı (sudo?)")?;
+        flush_caches();
+    }
+    Ok(format!("{} geri yüklendi (kaynak: {}).\n", path.display(), source))
+}
+
+fn flush_caches() {
+    let _ = Command::new("resolvectl").arg("flush-caches").output();
+    let _ = Command::new("nscd").arg("-i").arg("hosts").output();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_skips_names_that_are_not_hostnames() {
+        let names = vec!["youtube.com".to_string(), "a\n6.6.6.6 bank.com #.youtube.com".to_string(), "a b.com".to_string()];
+        let rendered = render("127.0.0.1 localhost\n", &names);
+        assert_eq!(rendered, "127.0.0.1 localhost\n# BEGIN FOCUS BLOCK\n127.0.0.1 youtube.com\n# END FOCUS BLOCK\n");
+        assert!(validate(&rendered).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_foreign_lines_in_block() {
+        let injected = "# BEGIN FOCUS BLOCK\n127.0.0.1 a\n6.6.6.6 bank.com #.youtube.com\n# END FOCUS BLOCK\n";
+        assert!(validate(injected).is_err());
+        let two_names = "# BEGIN FOCUS BLOCK\n127.0.0.1 a.com b.com\n# END FOCUS BLOCK\n";
+        assert!(validate(two_names).is_err());
+    }
+
+    #[test]
+    fn update_keeps_unrecognized_user_lines_byte_for_byte() {
+        let original = "127.0.0.1\tlocalhost\r\nfe80::1%lo0 localhost\r\n::1 ip6-localhost\n\n# not\x0c\n10.0.0.1 nas";
+        let names = vec!["example.org".to_string()];
+        let rendered = render(original, &names);
+        assert_eq!(rendered, format!("{}\n# BEGIN FOCUS BLOCK\n127.0.0.1 example.org\n# END FOCUS BLOCK\n", original));
+        assert!(check_rendered(original, &rendered).is_ok());
+        assert_eq!(strip_block(&rendered), format!("{}\n", original));
+        assert!(check_rendered(&rendered, &strip_block(&rendered)).is_ok());
+    }
+
+    #[test]
+    fn check_rendered_rejects_changes_outside_block() {
+        let original = "127.0.0.1 localhost\n";
+        assert!(check_rendered(original, "127.0.0.1  localhost\n").is_err());
+        assert!(check_rendered(original, "").is_err());
+        let block = "# BEGIN FOCUS BLOCK\n127.0.0.1 a.com\n# END FOCUS BLOCK\n";
+        assert!(check_rendered(original, &format!("{}{}", original, block)).is_ok());
+        assert!(check_rendered(original, &format!("{}{}", block, original)).is_ok());
+        assert!(check_rendered(original, &format!("{}{}6.6.6.6 bank.com\n", original, block)).is_err());
+    }
+}
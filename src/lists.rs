@@ -0,0 +1,174 @@
+// Toplu domain listeleri: hosts dosyası ("0.0.0.0 example.com"), satır başına bir domain ve
+// AdBlock ("||example.com^") biçimlerinin okunması ve yazılması.
+// Biçim satır satır tanındığı için karışık dosyalar da okunur. AdBlock kuralları alt alan
+// adlarını da kapsadığından *.domain olarak okunur.
+
+use std::collections::HashSet;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::domain;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// 0.0.0.0 example.com
+    Hosts,
+    /// Satır başına bir domain
+    Plain,
+    /// ||example.com^
+    Adblock,
+}
+
+// Hosts dosyalarında engel listesine ait olmayan standart isimler
+const IGNORED_NAMES: [&str; 8] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-allnodes",
+    "ip6-allrouters",
+];
+
+/// Okunan domainler ve anlaşılamayan satırlar (satır numarası, içerik)
+#[derive(Default)]
+pub struct Parsed {
+    pub domains: Vec<String>,
+    pub skipped: Vec<(usize, String)>,
+}
+
+pub fn parse(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    for (number, line) in content.lines().enumerate() {
+        match parse_line(line) {
+            Ok(domains) => parsed.domains.extend(domains),
+            Err(()) => parsed.skipped.push((number + 1, line.trim().to_string())),
+        }
+    }
+    parsed.domains.sort();
+    parsed.domains.dedup();
+    parsed
+}
+
+/// Satırdaki domainler; yorum ve boş satırlar için boş liste
+fn parse_line(line: &str) -> Result<Vec<String>, ()> {
+    let line = line.trim();
+    // "!" AdBlock yorumu, "[Adblock Plus 2.0]" başlığı
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') || line.starts_with('[') {
+        return Ok(vec![]);
+    }
+
+    if let Some(rule) = line.strip_prefix("||") {
+        // ||example.com^ veya ||example.com^$third-party; yol içeren kurallar domain engeli değildir
+        let name = rule.split(['^', '$']).next().unwrap_or_default();
+        return domain(name).map(|d| vec![format!("*.{}", d)]).ok_or(());
+    }
+    // İstisna (@@) ve sayfa öğesi gizleme (##) kuralları karşılığı olmadığı için okunmaz
+    if line.starts_with("@@") || line.contains("##") || line.contains("#@#") {
+        return Err(());
+    }
+
+    let line = line.split('#').next().unwrap_or_default();
+    let mut fields = line.split_whitespace();
+    let first = fields.next().unwrap_or_default();
+    if first.parse::<IpAddr>().is_ok() {
+        return fields
+            .filter(|name| !IGNORED_NAMES.contains(name))
+            .map(|name| domain(name).ok_or(()))
+            .collect();
+    }
+    if fields.next().is_some() {
+        return Err(());
+    }
+    match first.strip_prefix("*.") {
+        Some(base) => domain(base).and_then(|d| domain::normalize(&format!("*.{}", d)).ok()).map(|d| vec![d]).ok_or(()),
+        None => domain(first).map(|d| vec![d]).ok_or(()),
+    }
+}
+
+/// Kurallardaki biçimde domain (domain::normalize). focus www. önekini zaten engellediği için
+/// "www.x.com" x.com olarak okunur. Listelerde noktasız adlar .com tamamlanmaz, atlanır.
+fn domain(name: &str) -> Option<String> {
+    let name = name.trim_end_matches('.');
+    if !name.contains('.') {
+        return None;
+    }
+    domain::normalize(name).ok()
+}
+
+/// Dosyanın kendisi veya dizindeki tüm dosyalar (gizli dosyalar hariç, isim sırasıyla)
+pub fn read(path: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(path)
+            .with_context(|| format!("{} okunamadı", path.display()))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file() && !p.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    files
+        .into_iter()
+        .map(|file| {
+            let content = fs::read_to_string(&file).with_context(|| format!("{} okunamadı", file.display()))?;
+            Ok((file, content))
+        })
+        .collect()
+}
+
+/// Domainleri istenen biçimde yazar. Hosts biçimi joker karakter desteklemediği için
+/// her domainin engellenen isimleri (`names`) ayrı ayrı yazılır.
+pub fn render(domains: &[String], format: Format, names: impl Fn(&str) -> Vec<String>) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Hosts => {
+            out.push_str("# focus export (hosts)\n");
+            let mut written = HashSet::new();
+            for name in domains.iter().flat_map(|d| names(d)) {
+                if written.insert(name.clone()) {
+                    out.push_str(&format!("0.0.0.0 {}\n", name));
+                }
+            }
+        }
+        Format::Plain => {
+            out.push_str("# focus export\n");
+            for domain in domains {
+                out.push_str(&format!("{}\n", domain));
+            }
+        }
+        Format::Adblock => {
+            out.push_str("! focus export (AdBlock)\n");
+            let mut written = HashSet::new();
+            for domain in domains.iter().map(|d| d.trim_start_matches("*.")) {
+                if written.insert(domain) {
+                    out.push_str(&format!("||{}^\n", domain));
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_normalizes_www_and_case() {
+        let content = "0.0.0.0 WWW.Example.org example.org\n||www.ads.net^\n*.www.tracker.io\nwww.com\nlocalhost\n";
+        let parsed = parse(content);
+        assert_eq!(parsed.domains, ["*.ads.net", "*.tracker.io", "example.org", "www.com"]);
+        assert_eq!(parsed.skipped, [(5, "localhost".to_string())]);
+    }
+}
@@ -1,18 +1,52 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
 use std::fs::{self};
 use std::path::Path;
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use chrono::{Local, NaiveTime, DateTime};
+use std::time::{Duration, SystemTime};
+use chrono::{Local, NaiveTime, DateTime, Weekday};
 use anyhow::{Result, Context};
 
+mod blocker;
+mod control;
+mod dns;
+mod domain;
+mod firewall;
+mod hosts;
+mod lists;
+mod schedule;
+mod schema;
+mod state;
+mod subscriptions;
+mod watch;
+mod window;
+
 // --- AYARLAR ---
-const CONFIG_PATH: &str = "/etc/focus/config.json";
+const CONFIG_PATH: &str = "/etc/focus/config.toml";
+// 2. sürümden önceki JSON config; ilk okumada TOML'a çevrilir
+const LEGACY_CONFIG_PATH: &str = "/etc/focus/config.json";
+// Ek kural dosyaları (örn: ekiplerin dağıttığı engel listeleri), yalnızca okunur
+const CONF_DIR: &str = "/etc/focus/conf.d";
 const HOSTS_PATH: &str = "/etc/hosts";
-const MARKER_START: &str = "# BEGIN FOCUS BLOCK";
-const MARKER_END: &str = "# END FOCUS BLOCK";
+
+// Popüler siteler için bilinen alt alan adları (hosts dosyası wildcard desteklemez)
+const BUILTIN_SUBDOMAINS: &[(&str, &[&str])] = &[
+    ("youtube.com", &["m", "music", "gaming", "tv"]),
+    ("reddit.com", &["old", "new", "m", "i", "np"]),
+    ("twitter.com", &["mobile", "m"]),
+    ("x.com", &["mobile"]),
+    ("facebook.com", &["m", "web", "mobile", "mbasic", "touch"]),
+    ("instagram.com", &["m", "l"]),
+    ("tiktok.com", &["m", "vm"]),
+    ("twitch.tv", &["m", "clips"]),
+    ("linkedin.com", &["m"]),
+    ("wikipedia.org", &["en", "tr", "m", "en.m", "tr.m"]),
+];
 
 // Grayscale
 const MATRIX_GRAYSCALE: &str = "0.2126, 0.7152, 0.0722, 0.2126, 0.7152, 0.0722, 0.2126, 0.7152, 0.0722";
@@ -20,49 +54,207 @@ const MATRIX_NORMAL: &str = "1, 0, 0, 0, 1, 0, 0, 0, 1";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Rule {
+    /// Kural ve ekran kuralları için ortak kimlik; 0 ise yüklenirken atanır
+    #[serde(default)]
+    id: u32,
     domain: String,
     start_time: String,
     end_time: String,
-    exception_until: Option<DateTime<Local>>,
+    days: Vec<Weekday>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    /// conf.d'den geldiyse dosya adı (bu kurallar CLI ile değiştirilemez)
+    #[serde(skip)]
+    source: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct BwRule {
+    #[serde(default)]
+    id: u32,
+    start_time: String,
+    end_time: String,
+    enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TimeWindow {
     start_time: String,
     end_time: String,
+    days: Vec<Weekday>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Rule {
+    fn window(&self) -> Result<window::Window> {
+        window::Window::parse(&self.start_time, &self.end_time)
+    }
+}
+
+impl BwRule {
+    fn window(&self) -> Result<window::Window> {
+        window::Window::parse(&self.start_time, &self.end_time)
+    }
+}
+
+impl TimeWindow {
+    fn window(&self) -> Result<window::Window> {
+        window::Window::parse(&self.start_time, &self.end_time)
+    }
+}
+
+/// Bir grup siteyi ortak saat aralıklarıyla engelleyen isimli profil (work, study, sleep...)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Profile {
+    name: String,
+    domains: Vec<String>,
+    windows: Vec<TimeWindow>,
+    enabled: bool,
+    #[serde(skip)]
+    source: Option<String>,
+}
+
+/// Saat aralıklarında yalnızca listedeki domainlerin (ve alt alan adlarının) çözülmesine izin verir.
+/// Yalnızca DNS backend'i ile uygulanabilir; hosts dosyası "geri kalan her şeyi" engelleyemez.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Allowlist {
+    name: String,
+    domains: Vec<String>,
+    windows: Vec<TimeWindow>,
     enabled: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Bir engel listesine abonelik; liste bağlı profil aktifken veya kendi saat aralıklarında engellenir
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Subscription {
+    name: String,
+    /// Dosya yolu, file:// veya http(s) adresi
+    source: String,
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    windows: Vec<TimeWindow>,
+    #[serde(default = "default_refresh_minutes")]
+    refresh_minutes: i64,
+    /// Önbellekteki liste (subscriptions::LISTS_DIR)
+    #[serde(skip)]
+    domains: Vec<String>,
+}
+
+fn default_refresh_minutes() -> i64 {
+    24 * 60
+}
+
+/// Pomodoro tarzı oturum: çalışma aralıklarında engelle, molalarda serbest bırak
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Session {
+    started_at: DateTime<Local>,
+    work_minutes: i64,
+    break_minutes: i64,
+    cycles: u32,
+    domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SessionPhase {
+    Work(u32),
+    Break(u32),
+    Finished,
+}
+
+/// Engellemenin nasıl uygulanacağı (birden fazlası birlikte seçilebilir)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    /// /etc/hosts dosyasına yazar
+    Hosts,
+    /// Daemon içindeki yerel DNS sinkhole üzerinden engeller
+    Dns,
+    /// Engelli domainlerin IP adreslerine giden trafiği nftables ile reddeder
+    Nftables,
+    /// Hiçbir şeye dokunmaz, engellenecekleri yalnızca loglar
+    #[serde(rename = "dryrun")]
+    #[value(name = "dryrun")]
+    DryRun,
+}
+
+fn default_backends() -> Vec<Backend> {
+    vec![Backend::Hosts]
+}
+
+fn backend_names(backends: &[Backend]) -> String {
+    let names: Vec<String> = backends
+        .iter()
+        .filter_map(|b| b.to_possible_value().map(|v| v.get_name().to_string()))
+        .collect();
+    names.join(", ")
+}
+
+/// Eksik alanlar Default'taki değerleri alır; eski sürümlerin farkları schema modülündeki göçlerle giderilir
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 struct Config {
+    version: u64,
     rules: Vec<Rule>,
-    #[serde(default)] 
+    profiles: Vec<Profile>,
+    /// Domain başına ek engellenecek alt alan adları (örn: "youtube.com": ["m", "music"])
+    subdomains: BTreeMap<String, Vec<String>>,
     bw_rules: Vec<BwRule>,
-    #[serde(default)]
-    manual_bw_active: bool,
-    #[serde(default)]
+    /// Sıradaki kural kimliği; yalnızca artar, silinen kuralların kimlikleri yeniden verilmez
+    next_id: u32,
+    subscriptions: Vec<Subscription>,
+    allowlists: Vec<Allowlist>,
     exception_daily_limit: u32,
-    #[serde(default)]
-    exceptions_used_count: u32, 
-    #[serde(default = "default_date")]
-    last_exception_date: String, 
+    backends: Vec<Backend>,
+    dns: dns::DnsSettings,
+    /// Ayrı dosyada tutulur; yalnızca eski sürümden yükseltirken config içinden okunur
+    #[serde(skip_serializing)]
+    state: state::State,
+    /// conf.d dosyalarındaki alt alan adları
+    #[serde(skip)]
+    included_subdomains: BTreeMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// Aboneliklerin önbellekteki listelerini okur
+    fn load_lists(&mut self) {
+        for subscription in &mut self.subscriptions {
+            subscription.domains = subscriptions::cached(&subscription.name);
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: schema::CURRENT_VERSION,
             rules: vec![],
+            profiles: vec![],
+            subdomains: BTreeMap::new(),
             bw_rules: vec![],
-            manual_bw_active: false,
-            exception_daily_limit: 2, 
-            exceptions_used_count: 0,
-            last_exception_date: default_date(),
+            next_id: 1,
+            subscriptions: vec![],
+            allowlists: vec![],
+            exception_daily_limit: 2,
+            backends: default_backends(),
+            dns: dns::DnsSettings::default(),
+            state: state::State::default(),
+            included_subdomains: BTreeMap::new(),
         }
     }
 }
 
-fn default_date() -> String {
-    "1970-01-01".to_string()
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu,
+    Weekday::Fri, Weekday::Sat, Weekday::Sun,
+];
+
+// Eski configlerde "days" alanı yok, bunlar her gün geçerli sayılır
+fn all_days() -> Vec<Weekday> {
+    WEEK.to_vec()
 }
 
 #[derive(Parser)]
@@ -73,20 +265,30 @@ struct Cli {
     command: Commands,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
 enum Commands {
-    /// Kural ekle (Aynı domain için birden fazla saat aralığı ekleyebilirsin)
+    /// Kural ekle (Aynı domain için birden fazla saat aralığı ekleyebilirsin, *.domain tüm alt alan adlarını kapsar)
     #[command(aliases = ["a"])]
     Add {
         domain: String,
+        /// Başlangıç saati (HH:MM veya HH:MM:SS)
         start: String,
+        /// Bitiş saati, aralığa dahil değildir (başlangıçla aynıysa tüm gün)
         end: String,
+        /// Geçerli günler (örn: mon-fri, weekends, mon,wed,fri)
+        #[arg(long, default_value = "all")]
+        days: String,
     },
-    /// Bir siteye ait TÜM kuralları siler
+    /// Bir siteye ait TÜM kuralları siler (tek kural için: focus rule rm <id>)
     #[command(aliases = ["r", "rm"])]
     Remove {
         domain: String,
     },
+    /// Tek bir kuralı kimliğiyle düzenle, sil veya pasif et (kimlikler focus list'te görünür)
+    Rule {
+        #[command(subcommand)]
+        action: RuleAction,
+    },
     /// Geçici istisna tanımla
     #[command(aliases = ["e", "exc"])]
     Exception {
@@ -98,14 +300,139 @@ enum Commands {
         #[command(subcommand)]
         action: BwAction,
     },
+    /// Site gruplarını ortak saatlerle engelleyen profiller
+    #[command(aliases = ["p"])]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// Bir domain için ek engellenecek alt alan adları
+    #[command(aliases = ["sub"])]
+    Subdomain {
+        #[command(subcommand)]
+        action: SubdomainAction,
+    },
+    /// Pomodoro oturumu (çalışma/mola döngüleri)
+    #[command(aliases = ["s"])]
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+    /// Kuralları belirli bir zamana kadar gevşetilemez yap (örn: focus lock --until 18:00, --for 3h)
+    Lock {
+        /// Kilidin biteceği saat (HH:MM veya "YYYY-MM-DD HH:MM")
+        #[arg(long, conflicts_with = "duration")]
+        until: Option<String>,
+        /// Kilit süresi (örn: 3h, 90m, 1h30m)
+        #[arg(long = "for")]
+        duration: Option<String>,
+    },
+    /// Engelleme yöntemlerini seç/göster (örn: focus backend hosts nftables)
+    Backend {
+        backends: Vec<Backend>,
+        /// DNS sinkhole dinleme adresi (örn: 127.0.0.153:53)
+        #[arg(long)]
+        listen: Option<String>,
+        /// Engelli olmayan sorguların iletileceği DNS sunucusu (örn: 1.1.1.1:53)
+        #[arg(long)]
+        upstream: Option<String>,
+        /// Engelli isimlere verilecek yanıt
+        #[arg(long)]
+        sinkhole: Option<dns::Sinkhole>,
+    },
+    /// Dosya veya dizindeki domain listelerini içe aktar (hosts, satır başına domain veya AdBlock biçimi)
+    Import {
+        /// Liste dosyası veya dizini (dizindeki tüm dosyalar okunur)
+        path: String,
+        /// Siteleri bu profile ekle (profil yoksa oluşturulur); verilmezse her site için kural eklenir
+        #[arg(long)]
+        profile: Option<String>,
+        /// Kuralların veya yeni profilin saat aralığı (varsayılan: tüm gün)
+        #[arg(long, short = 'w')]
+        window: Option<String>,
+        #[arg(long, default_value = "all")]
+        days: String,
+        /// Dosyadan okunan domainler (dosya istemcide okunur, daemon'a liste gönderilir)
+        #[arg(skip)]
+        domains: Vec<String>,
+    },
+    /// Engel listesine abone ol, daemon listeyi düzenli olarak yeniler (örn: focus subscribe ads https://example.com/hosts -w 09:00-17:00)
+    Subscribe {
+        /// Abonelik adı
+        name: String,
+        /// Liste dosyası, file:// veya http(s) adresi
+        source: String,
+        /// Liste bu profil aktifken engellenir
+        #[arg(long, conflicts_with = "windows")]
+        profile: Option<String>,
+        /// Saat aralığı (birden fazla verilebilir, profil ve aralık verilmezse tüm gün)
+        #[arg(long = "window", short = 'w')]
+        windows: Vec<String>,
+        #[arg(long, default_value = "all")]
+        days: String,
+        /// Yenileme aralığı (örn: 12h, 1d)
+        #[arg(long, default_value = "1d")]
+        refresh: String,
+    },
+    /// Saat aralığında yalnızca listedeki sitelere izin ver (sınav/derin çalışma modu, dns backend'i gerekir)
+    #[command(aliases = ["allow"])]
+    Allowlist {
+        #[command(subcommand)]
+        action: AllowlistAction,
+    },
+    /// Aboneliği kaldır
+    Unsubscribe {
+        name: String,
+    },
+    /// Kurallardaki siteleri liste olarak dışa aktar
+    Export {
+        #[arg(long, value_enum, default_value = "hosts")]
+        format: lists::Format,
+        /// Sadece bu profilin siteleri
+        #[arg(long)]
+        profile: Option<String>,
+        /// Yazılacak dosya (verilmezse standart çıktı)
+        #[arg(long, short = 'o')]
+        output: Option<String>,
+    },
+    /// Config dosyası işlemleri
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// /etc/hosts yedekleri
+    Hosts {
+        #[command(subcommand)]
+        action: HostsAction,
+    },
     /// Kuralları listele
     #[command(aliases = ["ls"])]
     List,
+    /// Daemon, backend ve kurulu güvenlik duvarı kurallarının durumu
+    Status,
+    /// focus'un eklediği güvenlik duvarı kurallarını kaldırıp engellemeyi yeniden uygular
+    Repair,
     /// Arka plan servisi (Manuel çalıştırma)
     Daemon,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum ConfigAction {
+    /// Saat formatlarını, tekrar eden kuralları ve bilinmeyen alanları kontrol eder
+    Validate {
+        /// Kontrol edilecek dosya (varsayılan: /etc/focus/config.toml ve conf.d)
+        #[arg(long)]
+        file: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum HostsAction {
+    /// FOCUS bloğunu kaldırıp son sağlam yedeği geri yükler
+    Restore,
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
 enum ExceptionAction {
     /// İstisna kullan (örn: focus exception allow youtube 15)
     #[command(aliases = ["a"])]
@@ -119,7 +446,168 @@ enum ExceptionAction {
     },
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum ProfileAction {
+    /// Yeni profil oluştur (örn: focus profile add work youtube reddit --window 09:00-17:00 --days mon-fri)
+    #[command(aliases = ["a"])]
+    Add {
+        name: String,
+        domains: Vec<String>,
+        /// Saat aralığı (birden fazla verilebilir)
+        #[arg(long = "window", short = 'w', required = true)]
+        windows: Vec<String>,
+        #[arg(long, default_value = "all")]
+        days: String,
+    },
+    /// Profili düzenle (site ekle/çıkar, saat aralıklarını değiştir)
+    Edit {
+        name: String,
+        /// Eklenecek siteler
+        #[arg(long = "add", num_args = 1..)]
+        add: Vec<String>,
+        /// Çıkarılacak siteler
+        #[arg(long = "remove", num_args = 1..)]
+        remove: Vec<String>,
+        /// Verilirse mevcut saat aralıklarının yerini alır
+        #[arg(long = "window", short = 'w')]
+        windows: Vec<String>,
+        /// Verilirse (yeni ya da mevcut) tüm aralıkların günlerini belirler
+        #[arg(long)]
+        days: Option<String>,
+    },
+    /// Profili aktif et
+    Enable {
+        name: String,
+    },
+    /// Profili pasif et (silmeden)
+    Disable {
+        name: String,
+    },
+    /// Profili sil
+    #[command(aliases = ["rm"])]
+    Remove {
+        name: String,
+    },
+    /// Profil detaylarını göster (isim verilmezse hepsi)
+    Show {
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum AllowlistAction {
+    /// İzin listesi oluştur (örn: focus allowlist add exam docs.rs crates.io -w 09:00-12:00)
+    #[command(aliases = ["a"])]
+    Add {
+        name: String,
+        #[arg(required = true)]
+        domains: Vec<String>,
+        /// Saat aralığı (birden fazla verilebilir)
+        #[arg(long = "window", short = 'w', required = true)]
+        windows: Vec<String>,
+        #[arg(long, default_value = "all")]
+        days: String,
+    },
+    /// İzin listesini aktif et
+    Enable {
+        name: String,
+    },
+    /// İzin listesini pasif et (silmeden)
+    Disable {
+        name: String,
+    },
+    /// İzin listesini sil
+    #[command(aliases = ["rm"])]
+    Remove {
+        name: String,
+    },
+    /// İzin listelerini göster
+    Show,
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum SubdomainAction {
+    /// Alt alan adı ekle (örn: focus subdomain add youtube.com m music)
+    #[command(aliases = ["a"])]
+    Add {
+        domain: String,
+        #[arg(required = true)]
+        subdomains: Vec<String>,
+    },
+    /// Alt alan adı çıkar
+    #[command(aliases = ["rm"])]
+    Remove {
+        domain: String,
+        #[arg(required = true)]
+        subdomains: Vec<String>,
+    },
+    /// Bir domain için engellenecek tüm isimleri göster
+    #[command(aliases = ["ls"])]
+    List {
+        domain: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum SessionAction {
+    /// Oturum başlat (örn: focus session start --work 50 --break 10 --cycles 4)
+    Start {
+        /// Çalışma süresi (dakika)
+        #[arg(long, default_value_t = 25)]
+        work: i64,
+        /// Mola süresi (dakika)
+        #[arg(long = "break", default_value_t = 5)]
+        break_: i64,
+        #[arg(long, default_value_t = 4)]
+        cycles: u32,
+        /// Sadece bu profilin sitelerini engelle
+        #[arg(long, conflicts_with = "domains")]
+        profile: Option<String>,
+        /// Sadece bu siteleri engelle (verilmezse listedeki tüm siteler)
+        #[arg(long, num_args = 1..)]
+        domains: Vec<String>,
+    },
+    /// Oturum durumunu göster
+    Status,
+    /// Oturumu erken bitir (bir istisna hakkı harcar)
+    Stop,
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
+enum RuleAction {
+    /// Kuralın saatlerini veya günlerini değiştir (ekran kurallarında gün yoktur)
+    Edit {
+        id: u32,
+        /// Yeni başlangıç saati
+        #[arg(long)]
+        start: Option<String>,
+        /// Yeni bitiş saati
+        #[arg(long)]
+        end: Option<String>,
+        /// Yeni günler (örn: mon-fri)
+        #[arg(long)]
+        days: Option<String>,
+    },
+    /// Kuralı sil
+    #[command(aliases = ["rm"])]
+    Remove { id: u32 },
+    /// Kuralı silmeden devre dışı bırak; --until/--for ile verilen zamana kadar duraklat
+    #[command(aliases = ["pause"])]
+    Disable {
+        id: u32,
+        /// Duraklatmanın biteceği saat (HH:MM veya "YYYY-MM-DD HH:MM")
+        #[arg(long, conflicts_with = "duration")]
+        until: Option<String>,
+        /// Duraklatma süresi (örn: 3h, 90m, 1h30m)
+        #[arg(long = "for")]
+        duration: Option<String>,
+    },
+    /// Devre dışı veya duraklatılmış kuralı tekrar etkinleştir
+    #[command(aliases = ["resume"])]
+    Enable { id: u32 },
+}
+
+#[derive(Subcommand, Serialize, Deserialize, Debug)]
 enum BwAction {
     /// Manuel olarak Siyah/Beyaz modunu AÇ
     On,
@@ -127,7 +615,9 @@ enum BwAction {
     Off,
     /// Belirli saatler arasında otomatik Siyah/Beyaz yap
     Rule {
+        /// Başlangıç saati (HH:MM veya HH:MM:SS)
         start: String,
+        /// Bitiş saati, aralığa dahil değildir (başlangıçla aynıysa tüm gün)
         end: String,
     },
     /// Siyah/Beyaz kuralını sil (Tüm saat kurallarını temizler)
@@ -137,393 +627,1878 @@ enum BwAction {
 // --- YARDIMCI FONKSİYONLAR ---
 
 fn load_config() -> Result<Config> {
-    if !Path::new(CONFIG_PATH).exists() {
-        return Ok(Config::default());
-    }
-    let content = fs::read_to_string(CONFIG_PATH).context("Config okunamadı")?;
-    let config: Config = serde_json::from_str(&content).context("JSON hatası")?;
-    Ok(config)
+    load_config_files().map_err(|e| anyhow::anyhow!("{}", e))
 }
 
-fn save_config(config: &Config) -> Result<()> {
-    if let Some(parent) = Path::new(CONFIG_PATH).parent() {
-        fs::create_dir_all(parent)?;
+/// Yüklenemeyen dosya (config, conf.d parçası veya durum dosyası) ve hatası
+struct LoadError {
+    path: String,
+    error: anyhow::Error,
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {:#}", self.path, self.error)
     }
-    let content = serde_json::to_string_pretty(config)?;
-    fs::write(CONFIG_PATH, content)?;
-    Ok(())
 }
 
-fn update_hosts_file(rules: &[Rule]) -> Result<()> {
-    let now = Local::now();
-    let current_time = now.time();
-    
-    let mut domains_to_block = Vec::new();
-    
-    for rule in rules {
-        let start = NaiveTime::parse_from_str(&rule.start_time, "%H:%M")?;
-        let end = NaiveTime::parse_from_str(&rule.end_time, "%H:%M")?;
-        
-        let in_time_window = if start <= end {
-            current_time >= start && current_time <= end
-        } else {
-            current_time >= start || current_time <= end
-        };
+/// Hatayı verilen dosyaya bağlar (map_err ile)
+fn in_file(path: impl std::fmt::Display) -> impl FnOnce(anyhow::Error) -> LoadError {
+    move |error| LoadError { path: path.to_string(), error }
+}
 
-        if in_time_window {
-            let is_exception = match rule.exception_until {
-                Some(expiry) => expiry > now,
-                None => false,
-            };
+/// Config'i, conf.d parçalarını ve durum dosyasını okur; hata hangi dosyadaysa onu bildirir
+fn load_config_files() -> Result<Config, LoadError> {
+    let mut config = if Path::new(CONFIG_PATH).exists() {
+        let content = fs::read_to_string(CONFIG_PATH).context("Config okunamadı").map_err(in_file(CONFIG_PATH))?;
+        let (mut config, migrated_from) = parse_toml(&content).and_then(schema::parse).map_err(in_file(CONFIG_PATH))?;
+        match migrated_from {
+            // Eski sürüm yerinde yükseltilir (yazma izni yoksa yalnızca bellekte)
+            Some(version) => upgrade_config(&config, CONFIG_PATH, version),
+            None => config.state = state::load().map_err(in_file(state::STATE_PATH))?,
+        }
+        config
+    } else if Path::new(LEGACY_CONFIG_PATH).exists() {
+        let content = fs::read_to_string(LEGACY_CONFIG_PATH).context("Config okunamadı").map_err(in_file(LEGACY_CONFIG_PATH))?;
+        let value = serde_json::from_str(&content).context("JSON hatası").map_err(in_file(LEGACY_CONFIG_PATH))?;
+        let (config, migrated_from) = schema::parse(value).map_err(in_file(LEGACY_CONFIG_PATH))?;
+        upgrade_config(&config, LEGACY_CONFIG_PATH, migrated_from.unwrap_or(schema::CURRENT_VERSION));
+        config
+    } else {
+        Config { state: state::load().map_err(in_file(state::STATE_PATH))?, ..Config::default() }
+    };
 
-            if !is_exception {
-                domains_to_block.push(rule.domain.clone());
-            }
+    assign_rule_ids(&mut config).map_err(in_file(CONFIG_PATH))?;
+    load_includes(&mut config)?;
+    config.load_lists();
+    Ok(config)
+}
+
+/// Yükseltilmiş config'i eski dosyayı yedekleyerek yeni yerine yazar
+fn upgrade_config(config: &Config, path: &str, version: u64) {
+    let result = schema::backup(path, version).and_then(|backup| {
+        save_config(config)?;
+        state::save(&config.state)?;
+        if path != CONFIG_PATH {
+            fs::remove_file(path)?;
         }
+        Ok(backup)
+    });
+    match result {
+        Ok(backup) => eprintln!("Config {}. sürümden {}. sürüme yükseltildi (yedek: {})", version, schema::CURRENT_VERSION, backup),
+        Err(e) => eprintln!("Config yükseltilemedi: {:#}", e),
     }
-    
-    domains_to_block.sort();
-    domains_to_block.dedup();
+}
 
-    let hosts_content = fs::read_to_string(HOSTS_PATH).unwrap_or_default();
-    let old_block_count = hosts_content.lines().filter(|l| l.contains("127.0.0.1")).count();
-    let mut new_lines: Vec<String> = Vec::new();
-    let mut in_block = false;
+/// conf.d/*.toml dosyalarındaki kuralları, profilleri ve alt alan adlarını ekler (dosya adı sırasıyla)
+fn load_includes(config: &mut Config) -> Result<(), LoadError> {
+    for path in include_files() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let content = fs::read_to_string(&path).context("okunamadı").map_err(in_file(path.display()))?;
+        let fragment = parse_toml(&content).and_then(schema::parse_fragment).map_err(in_file(path.display()))?;
 
-    for line in hosts_content.lines() {
-        if line.trim() == MARKER_START { in_block = true; continue; }
-        if line.trim() == MARKER_END { in_block = false; continue; }
-        if !in_block { new_lines.push(line.to_string()); }
+        // Duraklatmalar kimlikle tutulduğu için kimlik config.toml ve diğer dosyalardaki kurallarla çakışmamalı
+        let mut ids: std::collections::HashSet<u32> = config.rules.iter().map(|r| r.id).chain(config.bw_rules.iter().map(|r| r.id)).collect();
+        if let Some(rule) = fragment.rules.iter().find(|r| r.id != 0 && !ids.insert(r.id)) {
+            return Err(LoadError {
+                path: path.display().to_string(),
+                error: anyhow::anyhow!("{} kimliği ({}) başka bir kuralda kullanılıyor", rule.id, rule.domain),
+            });
+        }
+        // CLI ile eklenen kurallar conf.d kimliklerini almasın
+        let max = fragment.rules.iter().map(|r| r.id).max().unwrap_or(0);
+        let next = max.checked_add(1).context("Kural kimliği en büyük değerde, daha küçük bir kimlik verin").map_err(in_file(path.display()))?;
+        config.next_id = config.next_id.max(next);
+        config.rules.extend(fragment.rules.into_iter().map(|rule| Rule { source: Some(name.clone()), ..rule }));
+        config.profiles.extend(fragment.profiles.into_iter().map(|profile| Profile { source: Some(name.clone()), ..profile }));
+        for (domain, subdomains) in fragment.subdomains {
+            config.included_subdomains.entry(domain).or_default().extend(subdomains);
+        }
     }
+    Ok(())
+}
 
-    if !domains_to_block.is_empty() {
-        new_lines.push(MARKER_START.to_string());
-        for domain in domains_to_block {
-            new_lines.push(format!("127.0.0.1 {}", domain));
-            new_lines.push(format!("127.0.0.1 www.{}", domain));
-        }
-        new_lines.push(MARKER_END.to_string());
+fn include_files() -> Vec<std::path::PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(CONF_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Config'in ana dosyaya yazılan kısmı (conf.d'den gelenler hariç)
+fn own_config(config: &Config) -> Config {
+    let mut own = config.clone();
+    own.rules.retain(|r| r.source.is_none());
+    own.profiles.retain(|p| p.source.is_none());
+    own.version = schema::CURRENT_VERSION;
+    own
+}
+
+/// TOML içeriğini schema göçlerinin işlediği JSON değerine çevirir
+fn parse_toml(content: &str) -> Result<serde_json::Value> {
+    let table: toml::Table = toml::from_str(content).context("TOML hatası")?;
+    Ok(toml_to_json(toml::Value::Table(table)))
+}
+
+/// Tarih/saat değerleri (örn: tırnaksız 09:00) metin olarak okunur
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(text) => text.into(),
+        toml::Value::Integer(number) => number.into(),
+        toml::Value::Float(number) => number.into(),
+        toml::Value::Boolean(flag) => flag.into(),
+        toml::Value::Datetime(datetime) => datetime.to_string().into(),
+        toml::Value::Array(items) => items.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table.into_iter().map(|(key, value)| (key, toml_to_json(value))).collect(),
     }
+}
 
-    let final_content = new_lines.join("\n");
-    let new_block_count = final_content.lines().filter(|l| l.contains("127.0.0.1")).count();
-    if final_content.trim() != hosts_content.trim() {
-        fs::write(HOSTS_PATH, final_content).context("Hosts dosyası yazılamadı (sudo?)")?;
-        if new_block_count > old_block_count {
-             kill_active_connections();
-        } else {
-            let _ = Command::new("resolvectl").arg("flush-caches").output();
-            let _ = Command::new("nscd").arg("-i").arg("hosts").output();
-        }
+/// Ana config dosyasını yazar; durum dosyası ayrıca state::save ile yazılır
+fn save_config(config: &Config) -> Result<()> {
+    let mut content = String::from(CONFIG_HEADER);
+    content.push_str(&toml::to_string(&own_config(config)).context("Config TOML'a çevrilemedi")?);
+    if let Some(parent) = Path::new(CONFIG_PATH).parent() {
+        fs::create_dir_all(parent)?;
     }
+    hosts::write_atomic(Path::new(CONFIG_PATH), &content).context("Config yazılamadı (salt okunur mu?)")
+}
 
+/// Yalnızca `before`dan bu yana değişen dosyaları yazar: istisna, oturum ve kilit gibi
+/// durum değişiklikleri config dosyasına dokunmaz, böylece config salt okunur olabilir
+fn save_changes(config: &Config, before: &Snapshot) -> Result<()> {
+    let after = Snapshot::of(config);
+    if after.config != before.config {
+        save_config(config)?;
+    }
+    if after.state != before.state {
+        state::save(&config.state)?;
+    }
     Ok(())
 }
 
-fn normalize_domain(input: &str) -> String {
-    if input.contains('.') {
-        input.to_string()
+const CONFIG_HEADER: &str = "\
+# focus ayarları. focus komutları bu dosyayı yeniden yazar, yorumlar korunmaz;
+# elle bakımı yapılan listeler için /etc/focus/conf.d/*.toml kullanın.
+
+";
+
+/// Dosya uzantısına göre JSON veya TOML olarak okur; conf.d içindeyse parça olarak doğrular
+fn validate_file(path: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return vec![format!("okunamadı: {}", e)],
+    };
+    let value = if path.extension().is_some_and(|e| e == "json") {
+        serde_json::from_str(&content).context("JSON hatası")
     } else {
-        format!("{}.com", input)
+        parse_toml(&content)
+    };
+    match value {
+        Err(e) => vec![format!("{:#}", e)],
+        Ok(value) if path.parent() == Some(Path::new(CONF_DIR)) => schema::validate_fragment(value),
+        Ok(value) => schema::validate(value),
     }
 }
 
-fn set_screen_grayscale(enable: bool) -> Result<()> {
-    let output = Command::new("xrandr")
-        .env("DISPLAY", ":0")
-        .arg("--current")
-        .output()?;
-    let output_str = String::from_utf8_lossy(&output.stdout);
+/// Config'in dosya başına karşılaştırılabilir hali (değişiklik ve kurcalama tespiti için)
+#[derive(PartialEq)]
+struct Snapshot {
+    /// Ana config dosyasına yazılan kısım
+    config: serde_json::Value,
+    /// conf.d'den gelen kurallar, profiller ve alt alan adları
+    includes: serde_json::Value,
+    state: serde_json::Value,
+}
 
-    for line in output_str.lines() {
-        if line.contains(" connected") {
-            let screen_name = line.split_whitespace().next().unwrap_or("default");
-            let matrix = if enable { MATRIX_GRAYSCALE } else { MATRIX_NORMAL };
-            println!("Setting {} to grayscale: {}", screen_name, enable);
-            let _ = Command::new("xrandr")
-                .env("DISPLAY", ":0")
-                .arg("--output")
-                .arg(screen_name)
-                .arg("--set")
-                .arg("CTM") // Color Transformation Matrix
-                .arg(matrix)
-                .spawn(); 
+impl Snapshot {
+    fn of(config: &Config) -> Self {
+        let included_rules: Vec<_> = config.rules.iter().filter(|r| r.source.is_some()).map(|r| (r, &r.source)).collect();
+        let included_profiles: Vec<_> =
+            config.profiles.iter().filter(|p| p.source.is_some()).map(|p| (p, &p.source)).collect();
+        Self {
+            config: serde_json::to_value(own_config(config)).unwrap_or_default(),
+            includes: serde_json::json!([included_rules, included_profiles, config.included_subdomains]),
+            state: serde_json::to_value(&config.state).unwrap_or_default(),
         }
     }
-    Ok(())
+
+    /// `other` ile farklı olan dosyalar
+    fn differing_files(&self, other: &Snapshot) -> Vec<&'static str> {
+        [
+            (self.config != other.config, CONFIG_PATH),
+            (self.includes != other.includes, CONF_DIR),
+            (self.state != other.state, state::STATE_PATH),
+        ]
+        .into_iter()
+        .filter(|(differs, _)| *differs)
+        .map(|(_, path)| path)
+        .collect()
+    }
+
+    fn to_key(&self) -> String {
+        format!("{}{}{}", self.config, self.includes, self.state)
+    }
 }
 
-fn update_screen_color(config: &Config, current_state: &mut Option<bool>) -> Result<()> {
-    let now = Local::now();
-    let current_time = now.time();
-    let mut should_be_bw = false;
+/// "mon-fri", "weekdays", "weekends", "sat,sun" veya "all" biçimindeki gün ifadesini çözer
+fn parse_days(input: &str) -> Result<Vec<Weekday>> {
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "all" | "everyday" | "daily" => return Ok(all_days()),
+        "weekdays" => return Ok(WEEK[..5].to_vec()),
+        "weekends" => return Ok(WEEK[5..].to_vec()),
+        _ => {}
+    }
 
-    if config.manual_bw_active {
-        should_be_bw = true;
-    } else {
-        for rule in &config.bw_rules {
-            if let (Ok(start), Ok(end)) = (
-                NaiveTime::parse_from_str(&rule.start_time, "%H:%M"),
-                NaiveTime::parse_from_str(&rule.end_time, "%H:%M")
-            ) {
-                 let in_time_window = if start <= end {
-                    current_time >= start && current_time <= end
-                } else {
-                    current_time >= start || current_time <= end
-                };
+    let parse_day = |s: &str| -> Result<Weekday> {
+        s.trim().parse::<Weekday>().map_err(|_| anyhow::anyhow!("Geçersiz gün: {}", s))
+    };
 
-                if in_time_window {
-                    should_be_bw = true;
-                    break; 
-                }
+    let mut days = Vec::new();
+    for part in input.split(',') {
+        if let Some((from, to)) = part.split_once('-') {
+            let mut day = parse_day(from)?;
+            let last = parse_day(to)?;
+            days.push(day);
+            while day != last {
+                day = day.succ();
+                days.push(day);
             }
+        } else {
+            days.push(parse_day(part)?);
         }
     }
 
-    if current_state.is_none() || current_state.unwrap() != should_be_bw {
-        set_screen_grayscale(should_be_bw)?;
-        *current_state = Some(should_be_bw);
-    }
+    days.sort_by_key(|d| d.num_days_from_monday());
+    days.dedup();
+    Ok(days)
+}
 
-    Ok(())
+fn format_days(days: &[Weekday]) -> String {
+    match days.len() {
+        7 => "Her gün".to_string(),
+        _ if days == &WEEK[..5] => "Hafta içi".to_string(),
+        _ if days == &WEEK[5..] => "Hafta sonu".to_string(),
+        _ => days.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(","),
+    }
 }
 
-fn cleanup_firewall() {
-    let commands = [
-        "iptables -w -D OUTPUT -p tcp --dport 443 -j REJECT --reject-with tcp-reset",
-        "iptables -w -D OUTPUT -p udp --dport 443 -j REJECT --reject-with icmp-port-unreachable",
-        "iptables -w -D OUTPUT -p tcp --dport 80 -j REJECT --reject-with tcp-reset",
+// Oturum ve istisna süreleri için üst sınırlar (zaman hesabının taşmaması için)
+const MAX_SESSION_MINUTES: i64 = 24 * 60;
+const MAX_SESSION_CYCLES: u32 = 100;
+const MAX_EXCEPTION_MINUTES: i64 = 24 * 60;
 
-        "iptables -w -D INPUT -p tcp --sport 443 -j REJECT --reject-with tcp-reset",
-        "iptables -w -D INPUT -p udp --sport 443 -j REJECT --reject-with icmp-port-unreachable",
-        "iptables -w -D INPUT -p tcp --sport 80 -j REJECT --reject-with tcp-reset",
+/// Oturumun şu anki evresi ve bu evrenin bitiş zamanı
+fn session_phase(session: &Session, now: DateTime<Local>) -> (SessionPhase, DateTime<Local>) {
+    let finished = (SessionPhase::Finished, now);
+    // Elle düzenlenmiş durum dosyasındaki aşırı değerler taşma yerine bitmiş oturum sayılır
+    let work = chrono::Duration::try_minutes(session.work_minutes);
+    let cycle = session.work_minutes.checked_add(session.break_minutes).and_then(chrono::Duration::try_minutes);
+    let (Some(work), Some(cycle)) = (work, cycle) else {
+        return finished;
+    };
+    let elapsed = now - session.started_at;
+    let index = elapsed.num_seconds() / cycle.num_seconds().max(1);
 
-        "ip6tables -w -D OUTPUT -p tcp --dport 443 -j REJECT --reject-with tcp-reset",
-        "ip6tables -w -D OUTPUT -p udp --dport 443 -j REJECT --reject-with icmp6-port-unreachable",
-        "ip6tables -w -D OUTPUT -p tcp --dport 80 -j REJECT --reject-with tcp-reset",
-        
-        "ip6tables -w -D INPUT -p tcp --sport 443 -j REJECT --reject-with tcp-reset",
-        "ip6tables -w -D INPUT -p udp --sport 443 -j REJECT --reject-with icmp6-port-unreachable",
-        "ip6tables -w -D INPUT -p tcp --sport 80 -j REJECT --reject-with tcp-reset",
-    ];
+    if elapsed < chrono::Duration::zero() || index >= session.cycles as i64 {
+        return finished;
+    }
+
+    let cycle_start = i32::try_from(index)
+        .ok()
+        .and_then(|index| cycle.checked_mul(index))
+        .and_then(|offset| session.started_at.checked_add_signed(offset));
+    let Some((work_end, cycle_end)) =
+        cycle_start.and_then(|start| Some((start.checked_add_signed(work)?, start.checked_add_signed(cycle)?)))
+    else {
+        return finished;
+    };
+    let index = index as u32;
+    if now < work_end {
+        (SessionPhase::Work(index + 1), work_end)
+    } else {
+        (SessionPhase::Break(index + 1), cycle_end)
+    }
+}
 
-    for cmd in commands {
-        let parts: Vec<&str> = cmd.split_whitespace().collect();
-        if let Some(program) = parts.get(0) {
-            let args = &parts[1..];
-            let _ = Command::new(program).args(args).output();
+fn describe_session(session: &Session) -> String {
+    let now = Local::now();
+    match session_phase(session, now) {
+        (SessionPhase::Finished, _) => "Oturum tamamlandı.\n".to_string(),
+        (phase, until) => {
+            let (label, cycle) = match phase {
+                SessionPhase::Work(c) => ("ÇALIŞMA", c),
+                SessionPhase::Break(c) => ("MOLA", c),
+                SessionPhase::Finished => unreachable!(),
+            };
+            let left = (until - now).num_seconds();
+            format!(
+                "{} ({}/{}) - {:02}:{:02} kaldı ({} bitişi)\nEngellenen site sayısı: {}\n",
+                label, cycle, session.cycles, left / 60, left % 60, until.format("%H:%M"), session.domains.len()
+            )
         }
     }
 }
 
-fn kill_active_connections() {
-    thread::spawn(|| {
-        let duration = Duration::from_secs(45);
-        let start = std::time::Instant::now();
+/// "09:00-17:00" biçimindeki aralığı çözer
+fn parse_window(input: &str, days: &[Weekday]) -> Result<TimeWindow> {
+    let (start, end) = input
+        .split_once('-')
+        .with_context(|| format!("Aralık formatı hatalı: {} (örn: 09:00-17:00)", input))?;
+    let (start, end) = (start.trim(), end.trim());
+    window::Window::parse(start, end)?;
+    Ok(TimeWindow {
+        start_time: start.to_string(),
+        end_time: end.to_string(),
+        days: days.to_vec(),
+    })
+}
 
-        cleanup_firewall();
+/// Değiştirilecek profil; conf.d'den gelen profiller yalnızca kendi dosyalarından değiştirilebilir
+fn find_profile<'a>(config: &'a mut Config, name: &str) -> Result<&'a mut Profile> {
+    let profile = config
+        .profiles
+        .iter_mut()
+        .find(|p| p.name == name)
+        .with_context(|| format!("{} isimli profil yok", name))?;
+    if profile.source.is_some() {
+        anyhow::bail!("{} profili {} dosyasından geliyor, oradan değiştirilmeli", name, included_path(&profile.source));
+    }
+    Ok(profile)
+}
 
-        let block_cmd = "
-        iptables -w -I OUTPUT 1 -p tcp --dport 443 -j REJECT --reject-with tcp-reset;
-        iptables -w -I OUTPUT 1 -p udp --dport 443 -j REJECT --reject-with icmp-port-unreachable;
-        iptables -w -I OUTPUT 1 -p tcp --dport 80 -j REJECT --reject-with tcp-reset;
-        iptables -w -I INPUT 1 -p tcp --sport 443 -j REJECT --reject-with tcp-reset;
-        iptables -w -I INPUT 1 -p udp --sport 443 -j REJECT --reject-with icmp-port-unreachable;
+/// Kimliği verilen kural: site kuralının veya ekran kuralının sırası
+enum RuleRef {
+    Site(usize),
+    Screen(usize),
+}
 
-        ip6tables -w -I OUTPUT 1 -p tcp --dport 443 -j REJECT --reject-with tcp-reset;
-        ip6tables -w -I OUTPUT 1 -p udp --dport 443 -j REJECT --reject-with icmp6-port-unreachable;
-        ip6tables -w -I OUTPUT 1 -p tcp --dport 80 -j REJECT --reject-with tcp-reset;
-        ip6tables -w -I INPUT 1 -p tcp --sport 443 -j REJECT --reject-with tcp-reset;
-        ip6tables -w -I INPUT 1 -p udp --sport 443 -j REJECT --reject-with icmp6-port-unreachable;
-        ";
-        let _ = Command::new("sh").arg("-c").arg(block_cmd).output();
+fn find_rule(config: &Config, id: u32) -> Result<RuleRef> {
+    if let Some(index) = config.rules.iter().position(|r| r.id == id && r.source.is_none()) {
+        return Ok(RuleRef::Site(index));
+    }
+    if let Some(index) = config.bw_rules.iter().position(|r| r.id == id) {
+        return Ok(RuleRef::Screen(index));
+    }
+    anyhow::bail!("{} numaralı kural yok (kimlikler focus list'te görünür, conf.d kuralları oradan değiştirilir)", id)
+}
 
-        while start.elapsed() < duration {
-            let nuke_cmd = "
-            ss -K dst :443;
-            ss -K dst :80;
-            ss -K -6 dst :443;
-            ss -K -6 dst :80;
+fn next_rule_id(config: &mut Config) -> Result<u32> {
+    let id = config.next_id;
+    config.next_id = id.checked_add(1).context("Kural kimlikleri tükendi (next_id en büyük değerde)")?;
+    Ok(id)
+}
 
-            conntrack -D -p tcp --dport 443 > /dev/null 2>&1;
-            conntrack -D -p udp --dport 443 > /dev/null 2>&1;
-            conntrack -D -p tcp --dport 80 > /dev/null 2>&1;
+/// Elle eklenmiş kimliksiz veya kimliği çakışan kurallara sıradaki kimliği verir.
+/// Sayaç kaydedilmemişse (eski config) veya elle geri alındıysa mevcut kimliklerin ötesine taşınır.
+fn assign_rule_ids(config: &mut Config) -> Result<()> {
+    let rules = config.rules.iter().filter(|r| r.source.is_none()).map(|r| r.id);
+    let max = rules.chain(config.bw_rules.iter().map(|r| r.id)).max().unwrap_or(0);
+    config.next_id = config.next_id.max(max.checked_add(1).context("Kural kimliği en büyük değerde, daha küçük bir kimlik verin")?);
 
-            conntrack -D -f ipv6 -p tcp --dport 443 > /dev/null 2>&1;
-            conntrack -D -f ipv6 -p udp --dport 443 > /dev/null 2>&1;
-            ";
+    let mut next = config.next_id;
+    let mut seen = std::collections::HashSet::new();
+    let rules = config.rules.iter_mut().filter(|r| r.source.is_none()).map(|r| &mut r.id);
+    for id in rules.chain(config.bw_rules.iter_mut().map(|r| &mut r.id)) {
+        if *id == 0 || !seen.insert(*id) {
+            *id = next;
+            next = next.checked_add(1).context("Kural kimlikleri tükendi (next_id en büyük değerde)")?;
+        }
+    }
+    config.next_id = next;
+    Ok(())
+}
 
-            let _ = Command::new("sh").arg("-c").arg(nuke_cmd).output();
+/// "aktif", "devre dışı" veya "duraklatıldı → 16.10 09:00"
+fn rule_status(enabled: bool, paused: Option<DateTime<Local>>) -> String {
+    match paused {
+        _ if !enabled => "devre dışı".to_string(),
+        Some(until) => format!("duraklatıldı → {}", until.format("%d.%m %H:%M")),
+        None => "aktif".to_string(),
+    }
+}
 
-            thread::sleep(Duration::from_secs(2));
-        }
+/// conf.d dosyasının tam yolu
+fn included_path(source: &Option<String>) -> String {
+    match source {
+        Some(name) => format!("{}/{}", CONF_DIR, name),
+        None => CONFIG_PATH.to_string(),
+    }
+}
 
-        cleanup_firewall();
-    });
+/// "09:00-17:00 (Hafta içi)"
+fn describe_window(window: &TimeWindow) -> String {
+    let range = window.window().map_or_else(|_| format!("{}-{}", window.start_time, window.end_time), |w| w.describe());
+    format!("{} ({})", range, format_days(&window.days))
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn describe_allowlist(allowlist: &Allowlist) -> String {
+    let mut out = String::new();
+    let status = if allowlist.enabled { "AKTİF" } else { "PASİF" };
+    let _ = writeln!(out, "[{}] {} (yalnızca bu siteler)", allowlist.name, status);
+    for window in &allowlist.windows {
+        let _ = writeln!(out, "  Zaman: {}", describe_window(window));
+    }
+    for domain in &allowlist.domains {
+        let _ = writeln!(out, "  + {}", domain);
+    }
+    out
+}
 
-    match cli.command {
-        Commands::Add { domain, start, end } => {
-            let mut config = load_config()?;
-            NaiveTime::parse_from_str(&start, "%H:%M").context("Saat formatı hatalı")?;
-            NaiveTime::parse_from_str(&end, "%H:%M").context("Saat formatı hatalı")?;
+fn describe_profile(profile: &Profile, state: &state::State) -> String {
+    let mut out = String::new();
+    let status = if profile.enabled { "AKTİF" } else { "PASİF" };
+    let _ = writeln!(out, "[{}] {}", profile.name, status);
+    if profile.source.is_some() {
+        let _ = writeln!(out, "  Kaynak: {}", included_path(&profile.source));
+    }
+    for window in &profile.windows {
+        let _ = writeln!(out, "  Zaman: {}", describe_window(window));
+    }
+    if profile.domains.is_empty() {
+        out.push_str("  (Site yok)\n");
+    }
+    for domain in &profile.domains {
+        let _ = match state.exception(domain, Local::now()) {
+            Some(t) => writeln!(out, "  - {} (istisna: {})", domain, t.format("%H:%M:%S")),
+            _ => writeln!(out, "  - {}", domain),
+        };
+    }
+    out
+}
 
-            let clean_domain = normalize_domain(&domain);
+fn lock_active(config: &Config) -> Option<DateTime<Local>> {
+    config.state.lock_until.filter(|until| *until > Local::now())
+}
 
-            config.rules.push(Rule {
-                domain: clean_domain.clone(),
-                start_time: start,
-                end_time: end,
-                exception_until: None,
+/// Kilit aktifken kuralları gevşeten işlemleri reddeder
+fn ensure_unlocked(config: &Config, action: &str) -> Result<()> {
+    if let Some(until) = lock_active(config) {
+        anyhow::bail!("Kilit {} tarihine kadar aktif, {} yapılamaz", until.format("%d.%m.%Y %H:%M"), action);
+    }
+    Ok(())
+}
+
+/// Kilit, duraklatma ve yenileme süreleri için üst sınır (daha uzunu zaman hesabında taşar)
+const MAX_DURATION_DAYS: i64 = 366;
+
+/// "3h", "90m", "1h30m" biçimindeki süreyi çözer
+fn parse_duration(input: &str) -> Result<chrono::Duration> {
+    let mut total = chrono::Duration::zero();
+    let mut number = String::new();
+    let too_long = || format!("Süre en fazla {} gün olabilir: {}", MAX_DURATION_DAYS, input);
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().with_context(|| format!("Süre formatı hatalı: {}", input))?;
+        let part = match c {
+            'd' => chrono::Duration::try_days(value),
+            'h' => chrono::Duration::try_hours(value),
+            'm' => chrono::Duration::try_minutes(value),
+            _ => anyhow::bail!("Süre formatı hatalı: {} (örn: 3h, 90m, 1h30m)", input),
+        };
+        total = part.and_then(|part| total.checked_add(&part)).with_context(too_long)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        // Birimsiz sayı dakika kabul edilir
+        let value: i64 = number.parse().with_context(|| format!("Süre formatı hatalı: {}", input))?;
+        total = chrono::Duration::try_minutes(value).and_then(|part| total.checked_add(&part)).with_context(too_long)?;
+    }
+    if total <= chrono::Duration::zero() {
+        anyhow::bail!("Süre pozitif olmalı: {}", input);
+    }
+    if total > chrono::Duration::days(MAX_DURATION_DAYS) {
+        anyhow::bail!(too_long());
+    }
+    Ok(total)
+}
+
+/// "18:00" (geçtiyse yarın) veya "2024-05-01 18:00" biçimindeki zamanı çözer
+fn parse_deadline(input: &str) -> Result<DateTime<Local>> {
+    let now = Local::now();
+    let naive = if let Ok(time) = NaiveTime::parse_from_str(input, "%H:%M") {
+        let mut date = now.date_naive();
+        if time <= now.time() {
+            date = date.succ_opt().context("Tarih hatası")?;
+        }
+        date.and_time(time)
+    } else {
+        chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+            .with_context(|| format!("Zaman formatı hatalı: {} (örn: 18:00)", input))?
+    };
+    let deadline = naive
+        .and_local_timezone(Local)
+        .earliest()
+        .with_context(|| format!("Geçersiz yerel zaman: {}", input))?;
+    if deadline > now + chrono::Duration::days(MAX_DURATION_DAYS) {
+        anyhow::bail!("Zaman en fazla {} gün sonrası olabilir: {}", MAX_DURATION_DAYS, input);
+    }
+    Ok(deadline)
+}
+
+/// Şu an engellenmesi gereken domainler (kurallar + aktif profiller)
+fn blocked_domains(config: &Config, now: DateTime<Local>) -> Result<Vec<String>> {
+    let mut domains_to_block = Vec::new();
+    
+    for rule in config.rules.iter().filter(|r| r.enabled && config.state.paused(r.id, now).is_none()) {
+        if rule.window()?.contains(&rule.days, now) && config.state.exception(&rule.domain, now).is_none() {
+            domains_to_block.push(rule.domain.clone());
+        }
+    }
+
+    for profile in &config.profiles {
+        if !profile_active(profile, now)? {
+            continue;
+        }
+
+        for domain in &profile.domains {
+            if config.state.exception(domain, now).is_none() {
+                domains_to_block.push(domain.clone());
+            }
+        }
+    }
+
+    for subscription in &config.subscriptions {
+        let active = match &subscription.profile {
+            Some(name) => match config.profiles.iter().find(|p| p.name == *name) {
+                Some(profile) => profile_active(profile, now)?,
+                None => false,
+            },
+            None => windows_active(&subscription.windows, now)?,
+        };
+        if active {
+            domains_to_block.extend(subscription.domains.iter().filter(|d| config.state.exception(d, now).is_none()).cloned());
+        }
+    }
+    
+    if let Some(session) = &config.state.session
+        && matches!(session_phase(session, now).0, SessionPhase::Work(_))
+    {
+        domains_to_block.extend(session.domains.iter().cloned());
+    }
+    
+    domains_to_block.sort();
+    domains_to_block.dedup();
+    Ok(domains_to_block)
+}
+
+/// Aktif izin listelerindeki domainler ve istisnalar; hiçbir izin listesi aktif değilse None
+fn allowed_domains(config: &Config, now: DateTime<Local>) -> Result<Option<Vec<String>>> {
+    let mut allowed = Vec::new();
+    let mut active = false;
+    for allowlist in config.allowlists.iter().filter(|a| a.enabled) {
+        if windows_active(&allowlist.windows, now)? {
+            active = true;
+            allowed.extend(allowlist.domains.iter().cloned());
+        }
+    }
+    if !active {
+        return Ok(None);
+    }
+    allowed.extend(config.state.exceptions.keys().filter(|d| config.state.exception(d, now).is_some()).cloned());
+    let mut allowed: Vec<String> = allowed.iter().map(|d| d.trim_start_matches("*.").to_string()).collect();
+    allowed.sort();
+    allowed.dedup();
+    Ok(Some(allowed))
+}
+
+fn profile_active(profile: &Profile, now: DateTime<Local>) -> Result<bool> {
+    Ok(profile.enabled && windows_active(&profile.windows, now)?)
+}
+
+fn windows_active(windows: &[TimeWindow], now: DateTime<Local>) -> Result<bool> {
+    for window in windows {
+        if window.window()?.contains(&window.days, now) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Hosts dosyasına yazılacak isimler: domain, www., yerleşik ve config'teki alt alan adları.
+/// "*.domain" kuralları için de aynı liste yazılır, geri kalanını DNS engeli yakalar.
+fn hosts_names(config: &Config, domain: &str) -> Vec<String> {
+    let base = domain.strip_prefix("*.").unwrap_or(domain);
+    let builtin = BUILTIN_SUBDOMAINS
+        .iter()
+        .filter(|(d, _)| *d == base)
+        .flat_map(|(_, subs)| subs.iter().map(|s| s.to_string()));
+    let configured = [&config.subdomains, &config.included_subdomains]
+        .into_iter()
+        .flat_map(|map| map.get(base).into_iter().flatten().cloned());
+
+    let mut names = vec![base.to_string(), format!("www.{}", base)];
+    for sub in builtin.chain(configured) {
+        let name = format!("{}.{}", sub, base);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Güncel kuralları config'te seçili tüm backend'lere uygular
+fn apply_blocking(config: &Config, blockers: &mut blocker::Blockers) -> Result<()> {
+    let set = blocker::BlockSet::compute(config, Local::now())?;
+    let added = blockers.apply(config, &set);
+    if added.is_empty() {
+        return Ok(());
+    }
+    // Daemon'da isimler durum kilidi tutulmadan çözülür; CLI ise çıkmadan önce bağlantıları kesmeli
+    if blockers.in_daemon() {
+        let upstream = config.dns.upstream.clone();
+        thread::spawn(move || firewall::cut_connections(&added, &upstream));
+    } else {
+        firewall::cut_connections(&added, &config.dns.upstream);
+    }
+    Ok(())
+}
+
+fn set_screen_grayscale(enable: bool) -> Result<()> {
+    let output = Command::new("xrandr")
+        .env("DISPLAY", ":0")
+        .arg("--current")
+        .output()?;
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    for line in output_str.lines() {
+        if line.contains(" connected") {
+            let screen_name = line.split_whitespace().next().unwrap_or("default");
+            let matrix = if enable { MATRIX_GRAYSCALE } else { MATRIX_NORMAL };
+            println!("Setting {} to grayscale: {}", screen_name, enable);
+            let _ = Command::new("xrandr")
+                .env("DISPLAY", ":0")
+                .arg("--output")
+                .arg(screen_name)
+                .arg("--set")
+                .arg("CTM") // Color Transformation Matrix
+                .arg(matrix)
+                .spawn(); 
+        }
+    }
+    Ok(())
+}
+
+fn update_screen_color(config: &Config, current_state: &mut Option<bool>) -> Result<()> {
+    let now = Local::now();
+    let should_be_bw = config.state.manual_bw_active
+        || config
+            .bw_rules
+            .iter()
+            .filter(|rule| rule.enabled && config.state.paused(rule.id, now).is_none())
+            .any(|rule| rule.window().is_ok_and(|w| w.contains(&WEEK, now)));
+
+    if current_state.is_none() || current_state.unwrap() != should_be_bw {
+        set_screen_grayscale(should_be_bw)?;
+        *current_state = Some(should_be_bw);
+    }
+
+    Ok(())
+}
+
+/// Config'i değiştiren komutları uygular ve kullanıcıya gösterilecek çıktıyı döner.
+/// Hem CLI (daemon çalışmıyorsa) hem de daemon (soket üzerinden) bunu kullanır.
+fn execute(config: &mut Config, command: Commands) -> Result<String> {
+    let mut out = String::new();
+
+    match command {
+        Commands::Add { domain, start, end, days } => {
+            let window = window::Window::parse(&start, &end)?;
+            let days = parse_days(&days)?;
+
+            let clean_domain = domain::normalize(&domain)?;
+
+            let id = next_rule_id(config)?;
+            config.rules.push(Rule {
+                id,
+                domain: clean_domain.clone(),
+                start_time: start,
+                end_time: end,
+                days,
+                enabled: true,
+                source: None,
             });
-            save_config(&config)?;
-            println!("Kural eklendi: {} ({}-{})", clean_domain, config.rules.last().unwrap().start_time, config.rules.last().unwrap().end_time);
+            let rule = config.rules.last().unwrap();
+            writeln!(out, "Kural eklendi (#{}): {} ({}, {})", rule.id, clean_domain, window.describe(), format_days(&rule.days))?;
         }
         
         Commands::Remove { domain } => {
-            let mut config = load_config()?;
-            let clean_domain = normalize_domain(&domain);
+            ensure_unlocked(config, "kural silme")?;
+            // Eski sürümlerin normalize etmeden kaydettiği kurallar yazıldığı gibi de silinebilir
+            let clean_domain = if config.rules.iter().any(|r| r.domain == domain) { domain } else { domain::normalize(&domain)? };
             let initial_len = config.rules.len();
-            config.rules.retain(|r| r.domain != clean_domain);
+            let removed: Vec<u32> = config.rules.iter().filter(|r| r.domain == clean_domain && r.source.is_none()).map(|r| r.id).collect();
+            config.rules.retain(|r| r.domain != clean_domain || r.source.is_some());
+            for id in removed {
+                config.state.paused.remove(&id);
+            }
 
             if config.rules.len() < initial_len {
-                save_config(&config)?;
-                println!("{} silindi", clean_domain);
-                let _ = update_hosts_file(&config.rules);
-            } else {
-                println!("{} bulunamadı", clean_domain);
+                writeln!(out, "{} silindi", clean_domain)?;
+            }
+            for rule in config.rules.iter().filter(|r| r.domain == clean_domain) {
+                writeln!(out, "{} kuralı {} dosyasından geliyor, oradan silinmeli", clean_domain, included_path(&rule.source))?;
+            }
+            if config.rules.len() == initial_len && !config.rules.iter().any(|r| r.domain == clean_domain) {
+                writeln!(out, "{} bulunamadı", clean_domain)?;
             }
         }
 
-        Commands::Exception { action } => {
-            let mut config = load_config()?;
+        Commands::Rule { action } => match action {
+            RuleAction::Edit { id, start, end, days } => {
+                if start.is_none() && end.is_none() && days.is_none() {
+                    anyhow::bail!("Değiştirilecek bir şey yok (--start, --end veya --days)");
+                }
+                let locked = lock_active(config).is_some();
+                match find_rule(config, id)? {
+                    RuleRef::Site(index) => {
+                        let rule = &config.rules[index];
+                        let mut edited = rule.clone();
+                        edited.start_time = start.unwrap_or(edited.start_time);
+                        edited.end_time = end.unwrap_or(edited.end_time);
+                        if let Some(days) = days {
+                            edited.days = parse_days(&days)?;
+                        }
+                        let (new, old) = (edited.window()?, rule.window()?);
+                        if locked && rule.enabled && !window::covers(&[(new, edited.days.clone())], &[(old, rule.days.clone())]) {
+                            anyhow::bail!("Kilit aktif, kuralın saat aralığı daraltılamaz");
+                        }
+                        writeln!(out, "#{} güncellendi: {} ({}, {})", id, edited.domain, new.describe(), format_days(&edited.days))?;
+                        config.rules[index] = edited;
+                    }
+                    RuleRef::Screen(index) => {
+                        if days.is_some() {
+                            anyhow::bail!("Siyah/Beyaz kurallarının günü yoktur, her gün geçerlidir");
+                        }
+                        let rule = &config.bw_rules[index];
+                        let mut edited = rule.clone();
+                        edited.start_time = start.unwrap_or(edited.start_time);
+                        edited.end_time = end.unwrap_or(edited.end_time);
+                        let (new, old) = (edited.window()?, rule.window()?);
+                        if locked && rule.enabled && !window::covers(&[(new, WEEK.to_vec())], &[(old, WEEK.to_vec())]) {
+                            anyhow::bail!("Kilit aktif, kuralın saat aralığı daraltılamaz");
+                        }
+                        writeln!(out, "#{} güncellendi: Siyah/Beyaz {}", id, new.describe())?;
+                        config.bw_rules[index] = edited;
+                    }
+                }
+            }
+            RuleAction::Remove { id } => {
+                ensure_unlocked(config, "kural silme")?;
+                config.state.paused.remove(&id);
+                match find_rule(config, id)? {
+                    RuleRef::Site(index) => {
+                        let rule = config.rules.remove(index);
+                        writeln!(out, "#{} silindi: {} ({}-{})", id, rule.domain, rule.start_time, rule.end_time)?;
+                    }
+                    RuleRef::Screen(index) => {
+                        let rule = config.bw_rules.remove(index);
+                        writeln!(out, "#{} silindi: Siyah/Beyaz {}-{}", id, rule.start_time, rule.end_time)?;
+                    }
+                }
+            }
+            RuleAction::Disable { id, until, duration } => {
+                ensure_unlocked(config, "kuralı devre dışı bırakma")?;
+                let rule = find_rule(config, id)?;
+                let deadline = match (until, duration) {
+                    (Some(until), _) => Some(parse_deadline(&until)?),
+                    (None, Some(duration)) => Some(Local::now() + parse_duration(&duration)?),
+                    (None, None) => None,
+                };
+                if let Some(deadline) = deadline {
+                    config.state.paused.insert(id, deadline);
+                    writeln!(out, "#{} {} tarihine kadar duraklatıldı.", id, deadline.format("%d.%m.%Y %H:%M"))?;
+                } else {
+                    match rule {
+                        RuleRef::Site(index) => config.rules[index].enabled = false,
+                        RuleRef::Screen(index) => config.bw_rules[index].enabled = false,
+                    }
+                    writeln!(out, "#{} devre dışı bırakıldı.", id)?;
+                }
+            }
+            RuleAction::Enable { id } => {
+                match find_rule(config, id)? {
+                    RuleRef::Site(index) => config.rules[index].enabled = true,
+                    RuleRef::Screen(index) => config.bw_rules[index].enabled = true,
+                }
+                config.state.paused.remove(&id);
+                writeln!(out, "#{} etkinleştirildi.", id)?;
+            }
+        },
+
+        Commands::Exception { action } => match action {
+            ExceptionAction::SetLimit { limit } => {
+                if limit > config.exception_daily_limit {
+                    ensure_unlocked(config, "istisna limitini artırma")?;
+                }
+                config.exception_daily_limit = limit;
+                writeln!(out, "Günlük istisna limiti {} olarak ayarlandı.", limit)?;
+            }
+            ExceptionAction::Allow { domain, minutes } => {
+                let clean_domain = domain::normalize(&domain)?;
+                if !(1..=MAX_EXCEPTION_MINUTES).contains(&minutes) {
+                    anyhow::bail!("İstisna süresi 1 ile {} dakika arasında olmalı", MAX_EXCEPTION_MINUTES);
+                }
 
-            match action {
-                ExceptionAction::SetLimit { limit } => {
-                    config.exception_daily_limit = limit;
-                    save_config(&config)?;
-                    println!("Günlük istisna limiti {} olarak ayarlandı.", limit);
+                if !config.state.exception_available(config.exception_daily_limit) {
+                    anyhow::bail!("Günlük istisna limitine ({}) ulaştınız!", config.exception_daily_limit);
                 }
-                ExceptionAction::Allow { domain, minutes } => {
-                    let clean_domain = normalize_domain(&domain);
-                    let today = Local::now().format("%Y-%m-%d").to_string();
 
-                    // Gün bitiminde sıfırlama
-                    if config.last_exception_date != today {
-                        config.exceptions_used_count = 0;
-                        config.last_exception_date = today.clone();
+                let now = Local::now();
+                // Oturum alanları istisnaya bakılmadan engellenir; hak boşa harcanmasın
+                if let Some(session) = &config.state.session
+                    && matches!(session_phase(session, now).0, SessionPhase::Work(_))
+                    && session.domains.iter().any(|d| dns::within(clean_domain.trim_start_matches("*."), d.trim_start_matches("*.")))
+                {
+                    anyhow::bail!("{} çalışma oturumunda engelli, istisna oturum bitince veya moladayken alınabilir", clean_domain);
+                }
+                // İzin listesi aktifken listede olmayan her site engellidir
+                let found = config.rules.iter().any(|r| r.domain == clean_domain && r.enabled)
+                    || config.profiles.iter().any(|p| p.domains.contains(&clean_domain))
+                    || config.subscriptions.iter().any(|s| s.domains.contains(&clean_domain))
+                    || allowed_domains(config, now)?.is_some_and(|allowed| !allowed.iter().any(|a| dns::within(clean_domain.trim_start_matches("*."), a)));
+
+                if found {
+                    config.state.prune(now);
+                    config.state.exceptions.insert(clean_domain.clone(), now + chrono::Duration::minutes(minutes));
+                    config.state.exceptions_used_count += 1;
+                    let remaining = config.exception_daily_limit - config.state.exceptions_used_count;
+                    writeln!(out, "Kalan istisna hakkı: {}", remaining)?;
+                } else {
+                    writeln!(out, "Hata: {} için engelleme kuralı yok", clean_domain)?;
+                }
+            }
+        },
+
+        Commands::Profile { action } => match action {
+            ProfileAction::Add { name, domains, windows, days } => {
+                if config.profiles.iter().any(|p| p.name == name) {
+                    anyhow::bail!("{} isimli profil zaten var", name);
+                }
+                let days = parse_days(&days)?;
+                let windows = windows
+                    .iter()
+                    .map(|w| parse_window(w, &days))
+                    .collect::<Result<Vec<_>>>()?;
+                let mut domains = domains.iter().map(|d| domain::normalize(d)).collect::<Result<Vec<_>>>()?;
+                domains.sort();
+                domains.dedup();
+
+                config.profiles.push(Profile {
+                    name: name.clone(),
+                    domains,
+                    windows,
+                    enabled: true,
+                    source: None,
+                });
+                out.push_str("Profil oluşturuldu:\n");
+                out.push_str(&describe_profile(config.profiles.last().unwrap(), &config.state));
+            }
+            ProfileAction::Edit { name, add, remove, windows, days } => {
+                let locked = lock_active(config).is_some();
+                if !remove.is_empty() {
+                    ensure_unlocked(config, "profilden site çıkarma")?;
+                }
+                let profile = find_profile(config, &name)?;
+                for domain in &add {
+                    let domain = domain::normalize(domain)?;
+                    if !profile.domains.contains(&domain) {
+                        profile.domains.push(domain);
                     }
+                }
+                for domain in &remove {
+                    if profile.domains.contains(domain) {
+                        profile.domains.retain(|d| d != domain);
+                    } else {
+                        let domain = domain::normalize(domain)?;
+                        profile.domains.retain(|d| *d != domain);
+                    }
+                }
+                profile.domains.sort();
+
+                let days = days.as_deref().map(parse_days).transpose()?;
+                let mut new_windows = profile.windows.clone();
+                if !windows.is_empty() {
+                    let days = days.clone().unwrap_or_else(all_days);
+                    new_windows = windows
+                        .iter()
+                        .map(|w| parse_window(w, &days))
+                        .collect::<Result<Vec<_>>>()?;
+                } else if let Some(days) = days {
+                    for window in new_windows.iter_mut() {
+                        window.days = days.clone();
+                    }
+                }
+                let spans = |windows: &[TimeWindow]| -> Result<Vec<_>> {
+                    windows.iter().map(|w| Ok((w.window()?, w.days.clone()))).collect()
+                };
+                if locked && !window::covers(&spans(&new_windows)?, &spans(&profile.windows)?) {
+                    anyhow::bail!("Kilit aktif, profilin saat aralıkları daraltılamaz");
+                }
+                profile.windows = new_windows;
+
+                let profile = profile.clone();
+                out.push_str("Profil güncellendi:\n");
+                out.push_str(&describe_profile(&profile, &config.state));
+            }
+            ProfileAction::Enable { ref name } | ProfileAction::Disable { ref name } => {
+                let enable = matches!(action, ProfileAction::Enable { .. });
+                if !enable {
+                    ensure_unlocked(config, "profili pasif etme")?;
+                }
+                find_profile(config, name)?.enabled = enable;
+                writeln!(out, "{} profili {}.", name, if enable { "aktif edildi" } else { "pasif edildi" })?;
+            }
+            ProfileAction::Remove { name } => {
+                ensure_unlocked(config, "profil silme")?;
+                if let Some(profile) = config.profiles.iter().find(|p| p.name == name && p.source.is_some()) {
+                    anyhow::bail!("{} profili {} dosyasından geliyor, oradan silinmeli", name, included_path(&profile.source));
+                }
+                let initial_len = config.profiles.len();
+                config.profiles.retain(|p| p.name != name);
+                if config.profiles.len() < initial_len {
+                    writeln!(out, "{} profili silindi", name)?;
+                } else {
+                    writeln!(out, "{} bulunamadı", name)?;
+                }
+            }
+            ProfileAction::Show { name } => match name {
+                Some(name) => {
+                    let profile = config.profiles.iter().find(|p| p.name == name).with_context(|| format!("{} isimli profil yok", name))?;
+                    out.push_str(&describe_profile(profile, &config.state));
+                }
+                None if config.profiles.is_empty() => out.push_str("Henüz hiç profil yok.\n"),
+                None => config.profiles.iter().for_each(|p| out.push_str(&describe_profile(p, &config.state))),
+            },
+        },
+
+        Commands::Subdomain { action } => match action {
+            SubdomainAction::Add { domain, subdomains } => {
+                let domain = domain::normalize(domain.trim_start_matches("*."))?;
+                let subdomains = subdomains.iter().map(|sub| domain::subdomain(sub, &domain)).collect::<Result<Vec<_>>>()?;
+                let entry = config.subdomains.entry(domain.clone()).or_default();
+                for sub in subdomains {
+                    if !entry.contains(&sub) {
+                        entry.push(sub);
+                    }
+                }
+                entry.sort();
+                writeln!(out, "{} için alt alan adları: {}", domain, entry.join(", "))?;
+            }
+            SubdomainAction::Remove { domain, subdomains } => {
+                ensure_unlocked(config, "alt alan adı çıkarma")?;
+                let domain = domain::normalize(domain.trim_start_matches("*."))?;
+                if let Some(entry) = config.subdomains.get_mut(&domain) {
+                    entry.retain(|s| !subdomains.contains(s));
+                    if entry.is_empty() {
+                        config.subdomains.remove(&domain);
+                    }
+                }
+                writeln!(out, "{} için alt alan adları güncellendi", domain)?;
+            }
+            SubdomainAction::List { domain } => {
+                let domains: Vec<String> = match domain {
+                    Some(domain) => vec![domain::normalize(domain.trim_start_matches("*."))?],
+                    None => {
+                        let mut domains: Vec<String> =
+                            config.subdomains.keys().chain(config.included_subdomains.keys()).cloned().collect();
+                        domains.sort();
+                        domains.dedup();
+                        domains
+                    }
+                };
+                if domains.is_empty() {
+                    out.push_str("Tanımlı alt alan adı yok.\n");
+                }
+                for domain in domains {
+                    writeln!(out, "{}: {}", domain, hosts_names(config, &domain).join(" "))?;
+                }
+            }
+        },
+
+        Commands::Session { action } => match action {
+            SessionAction::Start { work, break_, cycles, profile, domains } => {
+                if work <= 0 || break_ < 0 || cycles == 0 {
+                    anyhow::bail!("Süreler ve döngü sayısı pozitif olmalı");
+                }
+                if work > MAX_SESSION_MINUTES || break_ > MAX_SESSION_MINUTES || cycles > MAX_SESSION_CYCLES {
+                    anyhow::bail!("Süreler en fazla {} dakika, döngü sayısı en fazla {} olabilir", MAX_SESSION_MINUTES, MAX_SESSION_CYCLES);
+                }
+                if let Some(session) = &config.state.session
+                    && session_phase(session, Local::now()).0 != SessionPhase::Finished
+                {
+                    anyhow::bail!("Zaten devam eden bir oturum var (focus session status)");
+                }
+
+                let mut domains: Vec<String> = if let Some(name) = profile {
+                    find_profile(config, &name)?.domains.clone()
+                } else if !domains.is_empty() {
+                    domains.iter().map(|d| domain::normalize(d)).collect::<Result<_>>()?
+                } else {
+                    config.rules.iter().filter(|r| r.enabled).map(|r| r.domain.clone())
+                        .chain(config.profiles.iter().flat_map(|p| p.domains.iter().cloned()))
+                        .collect()
+                };
+                domains.sort();
+                domains.dedup();
+                if domains.is_empty() {
+                    anyhow::bail!("Engellenecek site yok (--domains ile belirtin)");
+                }
 
-                    if config.exceptions_used_count >= config.exception_daily_limit {
-                        eprintln!("Günlük istisna limitine ({}) ulaştınız!", config.exception_daily_limit);
-                        return Ok(());
+                config.state.session = Some(Session {
+                    started_at: Local::now(),
+                    work_minutes: work,
+                    break_minutes: break_,
+                    cycles,
+                    domains,
+                });
+                writeln!(out, "Oturum başladı: {} x ({} dk çalışma + {} dk mola)", cycles, work, break_)?;
+                out.push_str(&describe_session(config.state.session.as_ref().unwrap()));
+            }
+            SessionAction::Status => match &config.state.session {
+                Some(session) => out.push_str(&describe_session(session)),
+                None => out.push_str("Aktif oturum yok.\n"),
+            },
+            SessionAction::Stop => {
+                let Some(session) = &config.state.session else {
+                    out.push_str("Aktif oturum yok.\n");
+                    return Ok(out);
+                };
+
+                // Çalışma evresinde erken bitirmek istisna hakkı harcar
+                if matches!(session_phase(session, Local::now()).0, SessionPhase::Work(_)) {
+                    if !config.state.exception_available(config.exception_daily_limit) {
+                        anyhow::bail!("Günlük istisna limitine ({}) ulaştınız!", config.exception_daily_limit);
+                    }
+                    config.state.exceptions_used_count += 1;
+                    let remaining = config.exception_daily_limit - config.state.exceptions_used_count;
+                    writeln!(out, "Kalan istisna hakkı: {}", remaining)?;
+                }
+
+                config.state.session = None;
+                out.push_str("Oturum durduruldu.\n");
+            }
+        },
+
+        Commands::Lock { until, duration } => {
+            let deadline = match (until, duration) {
+                (Some(until), _) => parse_deadline(&until)?,
+                (None, Some(duration)) => Local::now() + parse_duration(&duration)?,
+                (None, None) => {
+                    match lock_active(config) {
+                        Some(until) => writeln!(out, "Kilit aktif: {}", until.format("%d.%m.%Y %H:%M"))?,
+                        None => out.push_str("Kilit yok.\n"),
                     }
+                    return Ok(out);
+                }
+            };
 
-                    let mut found = false;
-                    let expiry = Local::now() + chrono::Duration::minutes(minutes);
+            if let Some(current) = lock_active(config)
+                && deadline < current
+            {
+                anyhow::bail!("Kilit {} tarihine kadar aktif, kısaltılamaz", current.format("%d.%m.%Y %H:%M"));
+            }
 
-                    for rule in config.rules.iter_mut() {
-                        if rule.domain == clean_domain {
-                            rule.exception_until = Some(expiry);
-                            found = true;
+            config.state.lock_until = Some(deadline);
+            writeln!(out, "Kurallar {} tarihine kadar kilitlendi.", deadline.format("%d.%m.%Y %H:%M"))?;
+        }
+
+        Commands::Bw { action } => match action {
+            BwAction::On => {
+                config.state.manual_bw_active = true;
+                out.push_str("Ekran Siyah/Beyaz moda alındı.\n");
+            }
+            BwAction::Off => {
+                ensure_unlocked(config, "Siyah/Beyaz modu kapatma")?;
+                config.state.manual_bw_active = false;
+                out.push_str("Ekran Normal moda alındı.\n");
+            }
+            BwAction::Rule { start, end } => {
+                let window = window::Window::parse(&start, &end)?;
+                let id = next_rule_id(config)?;
+                config.bw_rules.push(BwRule {
+                    id,
+                    start_time: start.clone(),
+                    end_time: end.clone(),
+                    enabled: true
+                });
+                writeln!(out, "Siyah/Beyaz zaman kuralı eklendi (#{}): {}", id, window.describe())?;
+            }
+            BwAction::Clear => {
+                ensure_unlocked(config, "Siyah/Beyaz kurallarını silme")?;
+                for rule in config.bw_rules.drain(..) {
+                    config.state.paused.remove(&rule.id);
+                }
+                config.state.manual_bw_active = false; 
+                out.push_str("Tüm Siyah/Beyaz kuralları temizlendi.\n");
+            }
+        },
+
+        Commands::Backend { mut backends, listen, upstream, sinkhole } => {
+            if !backends.is_empty() {
+                backends.dedup();
+                if config.backends.iter().any(|b| !backends.contains(b)) {
+                    ensure_unlocked(config, "engelleme yöntemini kaldırma")?;
+                }
+                config.backends = backends;
+            }
+            if let Some(listen) = listen {
+                listen.parse::<std::net::SocketAddr>().context("Dinleme adresi hatalı")?;
+                config.dns.listen = listen;
+                out.push_str("Dinleme adresi daemon yeniden başlatılınca geçerli olur.\n");
+            }
+            if let Some(upstream) = upstream {
+                upstream.parse::<std::net::SocketAddr>().context("Upstream adresi hatalı")?;
+                config.dns.upstream = upstream;
+            }
+            if let Some(sinkhole) = sinkhole {
+                config.dns.sinkhole = sinkhole;
+            }
+
+            writeln!(out, "Engelleme yöntemleri: {}", backend_names(&config.backends))?;
+            if config.backends.contains(&Backend::Dns) {
+                writeln!(
+                    out,
+                    "DNS sinkhole: dinleme {}, upstream {}, yanıt {:?}",
+                    config.dns.listen, config.dns.upstream, config.dns.sinkhole
+                )?;
+                writeln!(out, "Sistemin DNS sunucusu olarak {} kullanılmalı.", config.dns.listen)?;
+            }
+            if config.backends.contains(&Backend::Nftables) {
+                writeln!(out, "nftables: domainler {} üzerinden çözülür.", config.dns.upstream)?;
+            }
+        }
+
+        Commands::Import { profile, window, days, domains, .. } => {
+            // Liste istemcide okunur; daemon'a gelen adlara güvenilmez, kurallardaki biçime çevrilir
+            let mut domains = domains.iter().map(|d| domain::normalize(d)).collect::<Result<Vec<_>>>()?;
+            domains.sort();
+            domains.dedup();
+            let days = parse_days(&days)?;
+            let explicit_window = window.is_some();
+            let window = parse_window(window.as_deref().unwrap_or("00:00-00:00"), &days)?;
+            let total = domains.len();
+
+            match profile {
+                Some(name) if config.profiles.iter().any(|p| p.name == name) => {
+                    if explicit_window {
+                        anyhow::bail!("{} profili zaten var, saatlerini değiştirmek için: focus profile edit {} --window", name, name);
+                    }
+                    let profile = find_profile(config, &name)?;
+                    let new: Vec<String> = domains.into_iter().filter(|d| !profile.domains.contains(d)).collect();
+                    writeln!(out, "{} profiline {} site eklendi ({} site zaten vardı)", name, new.len(), total - new.len())?;
+                    profile.domains.extend(new);
+                    profile.domains.sort();
+                }
+                Some(name) => {
+                    config.profiles.push(Profile {
+                        name: name.clone(),
+                        domains,
+                        windows: vec![window],
+                        enabled: true,
+                        source: None,
+                    });
+                    out.push_str("Profil oluşturuldu:\n");
+                    out.push_str(&describe_profile(config.profiles.last().unwrap(), &config.state));
+                }
+                None => {
+                    let mut added = 0;
+                    for domain in domains {
+                        let exists = config.rules.iter().any(|r| {
+                            r.domain == domain && r.start_time == window.start_time && r.end_time == window.end_time && r.days == days
+                        });
+                        if !exists {
+                            let id = next_rule_id(config)?;
+                            config.rules.push(Rule {
+                                id,
+                                domain,
+                                start_time: window.start_time.clone(),
+                                end_time: window.end_time.clone(),
+                                days: days.clone(),
+                                enabled: true,
+                                source: None,
+                            });
+                            added += 1;
                         }
                     }
+                    let range = window.window()?.describe();
+                    writeln!(out, "{} kural eklendi ({}, {}; {} kural zaten vardı)", added, range, format_days(&days), total - added)?;
+                }
+            }
+        }
 
-                    if found {
-                        config.exceptions_used_count += 1;
-                        save_config(&config)?;
+        Commands::Allowlist { action } => match action {
+            AllowlistAction::Add { name, domains, windows, days } => {
+                if config.allowlists.iter().any(|a| a.name == name) {
+                    anyhow::bail!("{} isimli izin listesi zaten var", name);
+                }
+                let days = parse_days(&days)?;
+                let windows = windows.iter().map(|w| parse_window(w, &days)).collect::<Result<Vec<_>>>()?;
+                let mut domains = domains.iter().map(|d| domain::normalize(d.trim_start_matches("*."))).collect::<Result<Vec<_>>>()?;
+                domains.sort();
+                domains.dedup();
 
-                        let remaining = config.exception_daily_limit - config.exceptions_used_count;
-                        println!("Kalan istisna hakkı: {}", remaining);
+                config.allowlists.push(Allowlist { name, domains, windows, enabled: true });
+                out.push_str("İzin listesi oluşturuldu:\n");
+                out.push_str(&describe_allowlist(config.allowlists.last().unwrap()));
+                if !config.backends.contains(&Backend::Dns) {
+                    out.push_str("Uyarı: izin listeleri yalnızca dns backend'i ile uygulanır (focus backend ... dns)\n");
+                }
+            }
+            AllowlistAction::Enable { ref name } | AllowlistAction::Disable { ref name } => {
+                let enable = matches!(action, AllowlistAction::Enable { .. });
+                if !enable {
+                    ensure_unlocked(config, "izin listesini pasif etme")?;
+                }
+                let allowlist = config
+                    .allowlists
+                    .iter_mut()
+                    .find(|a| a.name == *name)
+                    .with_context(|| format!("{} isimli izin listesi yok", name))?;
+                allowlist.enabled = enable;
+                writeln!(out, "{} izin listesi {}.", name, if enable { "aktif edildi" } else { "pasif edildi" })?;
+            }
+            AllowlistAction::Remove { name } => {
+                ensure_unlocked(config, "izin listesi silme")?;
+                let initial_len = config.allowlists.len();
+                config.allowlists.retain(|a| a.name != name);
+                if config.allowlists.len() < initial_len {
+                    writeln!(out, "{} izin listesi silindi", name)?;
+                } else {
+                    writeln!(out, "{} bulunamadı", name)?;
+                }
+            }
+            AllowlistAction::Show => {
+                if config.allowlists.is_empty() {
+                    out.push_str("Henüz hiç izin listesi yok.\n");
+                }
+                config.allowlists.iter().for_each(|a| out.push_str(&describe_allowlist(a)));
+            }
+        },
 
-                        let _ = update_hosts_file(&config.rules);
-                    } else {
-                        println!("Hata: {} için engelleme kuralı yok", clean_domain);
+        Commands::Subscribe { name, source, profile, windows, days, refresh } => {
+            if config.subscriptions.iter().any(|s| s.name == name) {
+                anyhow::bail!("{} isimli abonelik zaten var", name);
+            }
+            if !subscriptions::valid_name(&name) {
+                anyhow::bail!("Abonelik adı yalnızca harf, rakam, - ve _ içerebilir: {}", name);
+            }
+            if let Some(profile) = &profile
+                && !config.profiles.iter().any(|p| p.name == *profile)
+            {
+                anyhow::bail!("{} isimli profil yok", profile);
+            }
+            let days = parse_days(&days)?;
+            let mut windows = windows.iter().map(|w| parse_window(w, &days)).collect::<Result<Vec<_>>>()?;
+            if profile.is_none() && windows.is_empty() {
+                windows.push(parse_window("00:00-00:00", &days)?);
+            }
+            let refresh = parse_duration(&refresh)?;
+
+            let schedule = match &profile {
+                Some(profile) => format!("{} profiliyle", profile),
+                None => windows.iter().map(describe_window).collect::<Vec<_>>().join(", "),
+            };
+            writeln!(out, "Abonelik eklendi: {} ({}, {} dakikada bir yenilenir)", name, schedule, refresh.num_minutes())?;
+            config.subscriptions.push(Subscription {
+                name,
+                source,
+                profile,
+                windows,
+                refresh_minutes: refresh.num_minutes(),
+                domains: vec![],
+            });
+        }
+        Commands::Unsubscribe { name } => {
+            ensure_unlocked(config, "abonelik silme")?;
+            let initial_len = config.subscriptions.len();
+            config.subscriptions.retain(|s| s.name != name);
+            if config.subscriptions.len() < initial_len {
+                subscriptions::remove_cache(&name);
+                writeln!(out, "{} aboneliği silindi", name)?;
+            } else {
+                writeln!(out, "{} bulunamadı", name)?;
+            }
+        }
+
+        Commands::Export { .. } | Commands::Config { .. } | Commands::Hosts { .. } | Commands::List | Commands::Status | Commands::Repair | Commands::Daemon => anyhow::bail!("Bu komut daemon üzerinden çalıştırılamaz"),
+    }
+
+    Ok(out)
+}
+
+/// Daemon çalışmıyorsa komutu doğrudan config dosyası üzerinde uygular
+fn execute_local(command: Commands) -> Result<String> {
+    let mut config = load_config()?;
+    let before = Snapshot::of(&config);
+    let is_bw = matches!(command, Commands::Bw { .. });
+
+    let output = execute(&mut config, command)?;
+
+    if Snapshot::of(&config) != before {
+        save_changes(&config, &before)?;
+        let _ = apply_blocking(&config, &mut blocker::Blockers::new(false));
+        if is_bw {
+            update_screen_color(&config, &mut None)?;
+        }
+    }
+    Ok(output)
+}
+
+/// Daemon'un bellekte tuttuğu durum (soket iş parçacığı ile paylaşılır)
+struct DaemonState {
+    config: Config,
+    last_bw_state: Option<bool>,
+    last_session_phase: Option<SessionPhase>,
+    blockers: blocker::Blockers,
+    /// Diskteki config okunamıyorsa son hata; bu durumda bellekteki config dosyanın yerine yazılmaz
+    config_error: Option<String>,
+    /// Kilit altında reddedilen son disk hali; conf.d geri yazılamadığı için tekrar tekrar kaydedilmez
+    rejected: Option<String>,
+}
+
+impl DaemonState {
+    /// Config'i diskten tazeler, okunamazsa eldekini korur
+    fn reload(&mut self) {
+        if let Ok(config) = load_config() {
+            self.config = config;
+        }
+    }
+
+    /// Diskteki config (conf.d ve durum dosyası dahil) daemon'un bildiğinden farklıysa dışarıdan
+    /// düzenlenmiştir: kilit yokken düzenleme kabul edilir, kilit varken eldeki config geri yazılır.
+    /// Okunamayan dosya kilit yokken olduğu gibi bırakılır, son geçerli config ile devam edilir.
+    /// conf.d dosyaları geri yazılmaz, kilit bitene kadar yok sayılır.
+    fn sync_from_disk(&mut self) {
+        let on_disk = load_config_files();
+        let known = Snapshot::of(&self.config);
+        let disk = on_disk.as_ref().map(Snapshot::of);
+        let disk_key = match &disk {
+            Ok(disk) if *disk == known => {
+                self.rejected = None;
+                return;
+            }
+            Ok(disk) => disk.to_key(),
+            Err(e) => e.to_string(),
+        };
+        if self.config_error.is_none() && self.rejected.as_ref() == Some(&disk_key) {
+            return;
+        }
+
+        match on_disk {
+            // Hatalı dosya düzeltildi, ilk geçerli hali kurcalama sayılmaz
+            Ok(config) if self.config_error.is_some() => {
+                println!("Config yüklendi.");
+                self.config = config;
+                self.config_error = None;
+            }
+            Ok(config) if lock_active(&self.config).is_none() => {
+                let files = known.differing_files(&Snapshot::of(&config)).join(", ");
+                watch::record_tamper(&format!("{} dışarıdan değiştirildi (uygulandı)", files));
+                self.config = config;
+            }
+            Ok(config) => {
+                // Yalnızca değişen dosya geri yazılır (durum kurcalandıysa config'e dokunulmaz)
+                let disk = Snapshot::of(&config);
+                let files = known.differing_files(&disk).join(", ");
+                watch::record_tamper(&format!("{} kilit altında değiştirildi (geri alındı)", files));
+                if let Err(e) = save_changes(&self.config, &disk) {
+                    eprintln!("Config Hatası: {}", e);
+                }
+                self.rejected = Some(disk_key);
+            }
+            // Elde hiç geçerli config yoksa dosya ezilmez, yalnızca hata bildirilir
+            Err(e) if self.config_error.is_some() => {
+                let message = e.to_string();
+                if self.config_error.as_ref() != Some(&message) {
+                    eprintln!("Config Hatası: {} (focus config validate)", message);
+                    self.config_error = Some(message);
+                }
+            }
+            Err(e) if lock_active(&self.config).is_none() => {
+                eprintln!("Config Hatası: {} (focus config validate), son geçerli config ile devam ediliyor", e);
+                self.rejected = Some(disk_key);
+            }
+            // Kilit altında yalnızca bozulan dosya geri yazılır; bozuk hali yanına saklanır
+            Err(e) => {
+                if e.path == CONFIG_PATH || e.path == state::STATE_PATH {
+                    watch::record_tamper(&format!("{} kilit altında bozuldu (geri alındı)", e));
+                    let _ = fs::copy(&e.path, format!("{}.broken", e.path));
+                    let result = if e.path == CONFIG_PATH { save_config(&self.config) } else { state::save(&self.config.state) };
+                    if let Err(e) = result {
+                        eprintln!("Config Hatası: {}", e);
                     }
+                } else {
+                    watch::record_tamper(&format!("{} kilit altında bozuldu (kilit bitene kadar yok sayılıyor)", e));
                 }
+                self.rejected = Some(disk_key);
+            }
+        }
+    }
+
+    /// İzlenen bir dosya değişti: kurcalamayı kaydedip engellemeyi hemen yeniden uygular
+    fn file_changed(&mut self, path: &Path) {
+        if [CONFIG_PATH, CONF_DIR, state::STATE_PATH].iter().any(|p| path == Path::new(p)) {
+            self.sync_from_disk();
+        } else if path == Path::new(HOSTS_PATH) && self.config.backends.contains(&Backend::Hosts) {
+            let names = blocker::BlockSet::compute(&self.config, Local::now()).map(|s| s.names).unwrap_or_default();
+            if hosts::block_intact(path, &names) {
+                return;
             }
+            watch::record_tamper(&format!("{} içindeki FOCUS bloğu değiştirildi (yeniden yazıldı)", HOSTS_PATH));
+        }
+        self.apply();
+        schedule::wake();
+    }
+
+    /// Güncel config'e göre engellemeyi ve ekran rengini uygular
+    fn apply(&mut self) {
+        // Oturum evre geçişleri ve bitmiş oturumun temizlenmesi
+        let phase = self.config.state.session.as_ref().map(|s| session_phase(s, Local::now()).0);
+        if phase != self.last_session_phase {
+            match phase {
+                Some(SessionPhase::Work(c)) => println!("Oturum: çalışma evresi ({})", c),
+                Some(SessionPhase::Break(c)) => println!("Oturum: mola ({})", c),
+                Some(SessionPhase::Finished) => println!("Oturum tamamlandı."),
+                None => {}
+            }
+            self.last_session_phase = phase;
+        }
+        // Biten oturum ve süresi dolan istisnalar/kilit durum dosyasından temizlenir
+        let mut expired = self.config.state.prune(Local::now());
+        if phase == Some(SessionPhase::Finished) {
+            self.config.state.session = None;
+            expired = true;
+        }
+        if expired && let Err(e) = state::save(&self.config.state) {
+            eprintln!("Durum Hatası: {}", e);
         }
 
+        if let Err(e) = apply_blocking(&self.config, &mut self.blockers) {
+            eprintln!("Kural Hatası: {}", e);
+        }
+        if let Err(e) = update_screen_color(&self.config, &mut self.last_bw_state) {
+            eprintln!("Ekran Hatası (xrandr): {}", e);
+            self.last_bw_state = None;
+        }
+    }
+
+    /// Soketten gelen komutu uygular; config değiştiyse kaydedip hemen etkinleştirir
+    fn handle(&mut self, command: Commands) -> Result<String> {
+        self.sync_from_disk();
+
+        if let Commands::Repair = command {
+            let mut out = repair_firewall();
+            // Backend'lerin önbelleği sıfırlanmazsa silinen kurallar yeniden eklenmez
+            self.blockers = blocker::Blockers::new(true);
+            self.apply();
+            out.push_str("Engelleme yeniden uygulandı.\n");
+            return Ok(out);
+        }
+        if let Commands::Hosts { action: HostsAction::Restore } = command {
+            let mut out = hosts::restore(Path::new(HOSTS_PATH))?;
+            // Geri yüklenen dosyaya etkin engeller yeniden yazılır
+            self.blockers = blocker::Blockers::new(true);
+            self.apply();
+            out.push_str("Etkin engeller yeniden uygulandı.\n");
+            return Ok(out);
+        }
+
+        let before = Snapshot::of(&self.config);
+
+        let output = execute(&mut self.config, command);
+
+        if Snapshot::of(&self.config) != before {
+            if output.is_ok() {
+                save_changes(&self.config, &before)?;
+                self.apply();
+                schedule::wake();
+            } else {
+                // Hatalı komutun yarım bıraktığı değişiklikleri geri al
+                self.reload();
+            }
+        }
+        output
+    }
+}
+
+/// Günlükteki güvenlik duvarı değişikliklerini listeleyip geri alır
+fn repair_firewall() -> String {
+    let mut out = String::new();
+    for change in firewall::describe_journal() {
+        let _ = writeln!(out, "Kaldırılıyor: {}", change);
+    }
+    let undone = firewall::cleanup();
+    let _ = writeln!(out, "{} güvenlik duvarı değişikliği geri alındı.", undone);
+    out
+}
+
+/// Süresi dolan abonelik listelerini indirir; indirme sırasında soket için kilit tutulmaz.
+/// Başarısız olan listeler `LIST_RETRY` geçmeden yeniden denenmez.
+fn refresh_subscriptions(state: &Mutex<DaemonState>, failed: &mut HashMap<String, SystemTime>) {
+    let now = SystemTime::now();
+    let due: Vec<Subscription> = state
+        .lock()
+        .unwrap()
+        .config
+        .subscriptions
+        .iter()
+        .filter(|s| subscriptions::due(s, now))
+        .filter(|s| failed.get(&s.name).is_none_or(|t| now.duration_since(*t).unwrap_or_default() >= LIST_RETRY))
+        .cloned()
+        .collect();
+
+    let mut changed = false;
+    for subscription in &due {
+        match subscriptions::refresh(subscription) {
+            Ok(diff) => {
+                failed.remove(&subscription.name);
+                if !diff.added.is_empty() || !diff.removed.is_empty() {
+                    println!("Liste güncellendi: {} (+{}, -{})", subscription.name, diff.added.len(), diff.removed.len());
+                    changed = true;
+                }
+                if diff.skipped > 0 {
+                    println!("Liste {}: {} satır okunamadı", subscription.name, diff.skipped);
+                }
+            }
+            Err(e) => {
+                eprintln!("Liste Hatası ({}): {:#}", subscription.name, e);
+                failed.insert(subscription.name.clone(), now);
+            }
+        }
+    }
+    if changed {
+        let mut state = state.lock().unwrap();
+        state.config.load_lists();
+        state.apply();
+    }
+}
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+const MAX_SLEEP: chrono::Duration = chrono::Duration::seconds(60);
+const LIST_RETRY: Duration = Duration::from_secs(15 * 60);
+
+/// SIGINT/SIGTERM tüm iş parçacıklarında engellenir ve ayrı bir iş parçacığında sigwait ile beklenir;
+/// sinyal gelince bayrak set edilip zamanlayıcı uyandırılır, temizliği ana döngü yapar.
+/// Yeni iş parçacıkları sinyal maskesini devraldığı için hepsinden önce çağrılmalı.
+fn handle_shutdown_signals() -> Result<()> {
+    // SAFETY: sigset yerel değişkende başlatılır ve yalnızca libc çağrılarına verilir
+    let signals = unsafe {
+        let mut signals = std::mem::zeroed::<libc::sigset_t>();
+        libc::sigemptyset(&mut signals);
+        libc::sigaddset(&mut signals, libc::SIGINT);
+        libc::sigaddset(&mut signals, libc::SIGTERM);
+        let ret = libc::pthread_sigmask(libc::SIG_BLOCK, &signals, std::ptr::null_mut());
+        if ret != 0 {
+            return Err(std::io::Error::from_raw_os_error(ret)).context("Sinyaller engellenemedi");
+        }
+        signals
+    };
+
+    thread::spawn(move || {
+        let mut signal = 0;
+        // SAFETY: signals yukarıda başlatıldı, signal yerel değişken
+        while unsafe { libc::sigwait(&signals, &mut signal) } != 0 {}
+        SHUTDOWN.store(true, Ordering::SeqCst);
+        schedule::wake();
+    });
+    Ok(())
+}
+
+fn run_daemon() -> Result<()> {
+    handle_shutdown_signals()?;
+    println!("Focus Daemon çalışıyor...");
+    // Önceki çalışma çöktüyse bıraktığı kurallar günlükten temizlenir
+    let undone = firewall::cleanup();
+    if undone > 0 {
+        println!("Önceki çalışmadan kalan {} güvenlik duvarı değişikliği temizlendi.", undone);
+    }
+
+    let (config, config_error) = match load_config() {
+        Ok(config) => (config, None),
+        Err(e) => {
+            eprintln!("Config Hatası: {:#} (focus config validate), boş config ile devam ediliyor", e);
+            (Config::default(), Some(format!("{:#}", e)))
+        }
+    };
+    let state = Arc::new(Mutex::new(DaemonState {
+        config,
+        last_bw_state: None,
+        last_session_phase: None,
+        blockers: blocker::Blockers::new(true),
+        config_error,
+        rejected: None,
+    }));
+
+    if let Err(e) = control::serve(Arc::clone(&state)) {
+        eprintln!("Kontrol soketi açılamadı: {}", e);
+    }
+    // İzleme açılamazsa değişiklikler yine de bir sonraki turda fark edilir
+    if let Err(e) = watch::start(&[HOSTS_PATH, CONFIG_PATH, state::STATE_PATH], &[CONF_DIR], Arc::clone(&state)) {
+        eprintln!("Dosya izleme başlatılamadı: {:#}", e);
+    }
+
+    let mut failed_refreshes = HashMap::new();
+    loop {
+        refresh_subscriptions(&state, &mut failed_refreshes);
+        let deadline = {
+            let mut state = state.lock().unwrap();
+            state.sync_from_disk();
+            state.apply();
+            // Geçiş yoksa da ara sıra uyanılır (izlenmeyen değişiklikler, nft adreslerinin tazelenmesi)
+            let now = Local::now();
+            let latest = now + MAX_SLEEP;
+            schedule::next_transition(&state.config, now).map_or(latest, |next| next.min(latest))
+        };
+
+        loop {
+            if SHUTDOWN.load(Ordering::SeqCst) {
+                // Sokete gelen istekler bitsin diye kilit alınarak kapatılır
+                let _guard = state.lock().unwrap();
+                firewall::cleanup();
+                let _ = fs::remove_file(control::SOCKET_PATH);
+                println!("Focus Daemon durduruldu.");
+                return Ok(());
+            }
+            let Ok(left) = (deadline - Local::now()).to_std() else { break };
+            if schedule::wait(left) && !SHUTDOWN.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
         Commands::List => {
             let config = load_config()?;
+            if let Some(until) = lock_active(&config) {
+                println!("KİLİTLİ: {} tarihine kadar\n", until.format("%d.%m.%Y %H:%M"));
+            }
             println!("--- SİTE ENGELLEME KURALLARI ---");
             if config.rules.is_empty() {
                 println!("Henüz hiç kural yok.");
             } else {
-                println!("{:<20} {:<10} {:<10} {:<20}", "DOMAIN", "BAŞLA", "BİTİŞ", "İSTİSNA SONU");
-                for rule in config.rules {
-                    let exc = match rule.exception_until {
-                        Some(t) if t > Local::now() => t.format("%H:%M:%S").to_string(),
-                        _ => "-".to_string()
+                println!("{:<5} {:<20} {:<10} {:<10} {:<28} {:<14} {:<26} {:<20}", "ID", "DOMAIN", "BAŞLA", "BİTİŞ", "GÜNLER", "İSTİSNA SONU", "DURUM", "KAYNAK");
+                for rule in &config.rules {
+                    let exc = match config.state.exception(&rule.domain, Local::now()) {
+                        Some(t) => t.format("%H:%M:%S").to_string(),
+                        None => "-".to_string()
                     };
-                    println!("{:<20} {:<10} {:<10} {:<20}", rule.domain, rule.start_time, rule.end_time, exc);
+                    let source = rule.source.as_deref().unwrap_or("-");
+                    // conf.d kuralları CLI ile değiştirilemediği için kimlik gösterilmez
+                    let id = if rule.source.is_none() { rule.id.to_string() } else { "-".to_string() };
+                    let status = rule_status(rule.enabled, config.state.paused(rule.id, Local::now()));
+                    println!("{:<5} {:<20} {:<10} {:<10} {:<28} {:<14} {:<26} {:<20}", id, rule.domain, rule.start_time, rule.end_time, format_days(&rule.days), exc, status, source);
                 }
             }
 
+            println!("\n--- PROFİLLER ---");
+            if config.profiles.is_empty() { println!("(Profil yok)"); }
+            for profile in &config.profiles {
+                print!("{}", describe_profile(profile, &config.state));
+            }
+
+            if !config.allowlists.is_empty() {
+                println!("\n--- İZİN LİSTELERİ ---");
+            }
+            for allowlist in &config.allowlists {
+                print!("{}", describe_allowlist(allowlist));
+            }
+
+            if !config.subscriptions.is_empty() {
+                println!("\n--- ABONELİKLER ---");
+            }
+            for subscription in &config.subscriptions {
+                let schedule = match &subscription.profile {
+                    Some(profile) => format!("{} profiliyle", profile),
+                    None => subscription.windows.iter().map(describe_window).collect::<Vec<_>>().join(", "),
+                };
+                let updated = subscriptions::updated_at(&subscription.name)
+                    .map_or("henüz indirilmedi".to_string(), |t| DateTime::<Local>::from(t).format("%d.%m.%Y %H:%M").to_string());
+                println!("[{}] {} site, {} ({})", subscription.name, subscription.domains.len(), schedule, updated);
+                println!("  Kaynak: {}", subscription.source);
+            }
+
+            if let Some(session) = &config.state.session {
+                println!("\n--- OTURUM ---");
+                print!("{}", describe_session(session));
+            }
+
             println!("\n--- EKRAN KURALLARI (Siyah/Beyaz) ---");
-            if config.manual_bw_active { println!("MANUEL MOD: AÇIK"); }
+            if config.state.manual_bw_active { println!("MANUEL MOD: AÇIK"); }
             if config.bw_rules.is_empty() { println!("(Zaman kuralı yok)"); }
             for rule in &config.bw_rules {
-                println!("Zaman: {} - {}", rule.start_time, rule.end_time);
-            }
-        }
-
-        Commands::Bw { action } => {
-            let mut config = load_config()?;
-            match action {
-                BwAction::On => {
-                    config.manual_bw_active = true;
-                    save_config(&config)?;
-                    println!("Ekran Siyah/Beyaz moda alındı.");
-                    set_screen_grayscale(true)?;
-                }
-                BwAction::Off => {
-                    config.manual_bw_active = false;
-                    save_config(&config)?;
-                    println!("Ekran Normal moda alındı.");
-                    set_screen_grayscale(false)?;
-                }
-                BwAction::Rule { start, end } => {
-                    NaiveTime::parse_from_str(&start, "%H:%M")?;
-                    NaiveTime::parse_from_str(&end, "%H:%M")?;
-                    config.bw_rules.push(BwRule {
-                        start_time: start.clone(),
-                        end_time: end.clone(),
-                        enabled: true
-                    });
-                    save_config(&config)?;
-                    println!("Siyah/Beyaz zaman kuralı eklendi: {} - {}", start, end);
+                println!("#{} Zaman: {} - {} ({})", rule.id, rule.start_time, rule.end_time, rule_status(rule.enabled, config.state.paused(rule.id, Local::now())));
+            }
+
+            let tampers = watch::recent_tampers(10);
+            if !tampers.is_empty() {
+                println!("\n--- KURCALAMA KAYITLARI ---");
+                for line in tampers {
+                    println!("{}", line);
+                }
+            }
+        }
+
+        Commands::Status => {
+            let config = load_config().unwrap_or_default();
+            let daemon = if control::daemon_running() { "çalışıyor" } else { "çalışmıyor" };
+            println!("Daemon: {}", daemon);
+            println!("Engelleme yöntemleri: {}", backend_names(&config.backends));
+            println!();
+            print!("{}", firewall::status());
+        }
+
+        Commands::Daemon => run_daemon()?,
+
+        Commands::Config { action: ConfigAction::Validate { file } } => {
+            let all = file.is_none();
+            let files = match file {
+                Some(file) => vec![std::path::PathBuf::from(file)],
+                None => {
+                    let main = if Path::new(CONFIG_PATH).exists() || !Path::new(LEGACY_CONFIG_PATH).exists() {
+                        CONFIG_PATH
+                    } else {
+                        LEGACY_CONFIG_PATH
+                    };
+                    std::iter::once(main.into()).chain(include_files()).collect()
+                }
+            };
+
+            let mut total = 0;
+            for path in &files {
+                let problems = validate_file(path);
+                if problems.is_empty() {
+                    println!("{} geçerli.", path.display());
+                    continue;
+                }
+                println!("{}:", path.display());
+                for problem in &problems {
+                    println!("- {}", problem);
                 }
-                BwAction::Clear => {
-                    config.bw_rules.clear();
-                    config.manual_bw_active = false; 
-                    save_config(&config)?;
-                    println!("Tüm Siyah/Beyaz kuralları temizlendi.");
-                    set_screen_grayscale(false)?;
+                total += problems.len();
+            }
+            // Dosyalar arası sorunlar (örn: conf.d'de config.toml ile çakışan kimlik) ancak birlikte yüklenince görünür
+            if all
+                && total == 0
+                && let Err(e) = load_config_files()
+            {
+                println!("{}", e);
+                total += 1;
+            }
+            if total > 0 {
+                anyhow::bail!("{} sorun bulundu", total);
+            }
+        }
+
+        Commands::Hosts { action } => match control::send(&Commands::Hosts { action }) {
+            Some(output) => print!("{}", output?),
+            None => {
+                // Daemon yoksa blok geri eklenmeyeceği için kilit altında izin verilmez
+                ensure_unlocked(&load_config()?, "hosts engelini kaldırma")?;
+                print!("{}", hosts::restore(Path::new(HOSTS_PATH))?);
+            }
+        },
+
+        Commands::Repair => match control::send(&Commands::Repair) {
+            Some(output) => print!("{}", output?),
+            None => print!("{}", repair_firewall()),
+        },
+
+        Commands::Import { path, profile, window, days, .. } => {
+            let mut domains = Vec::new();
+            for (file, content) in lists::read(Path::new(&path))? {
+                let parsed = lists::parse(&content);
+                if let Some((line, text)) = parsed.skipped.first() {
+                    eprintln!(
+                        "{}: {} satır okunamadı (ilki {}. satır: {})",
+                        file.display(),
+                        parsed.skipped.len(),
+                        line,
+                        text
+                    );
                 }
+                domains.extend(parsed.domains);
+            }
+            domains.sort();
+            domains.dedup();
+            if domains.is_empty() {
+                anyhow::bail!("{} içinde domain bulunamadı", path);
             }
+            run(Commands::Import { path, profile, window, days, domains })?;
         }
 
-        Commands::Daemon => {
-            println!("Focus Daemon çalışıyor...");
-            cleanup_firewall();
-            let mut last_bw_state: Option<bool> = None;
-            loop {
-                if let Ok(config) = load_config() {
-                    if let Err(e) = update_hosts_file(&config.rules) {
-                        eprintln!("Hosts Hatası: {}", e);
-                    }
-                    if let Err(e) = update_screen_color(&config, &mut last_bw_state) {
-                        eprintln!("Ekran Hatası (xrandr): {}", e);
-                        last_bw_state = None;
-                    }
+        Commands::Subscribe { name, source, profile, windows, days, refresh } => {
+            // Dosya yolları daemon'un çalışma dizininden bağımsız olsun diye tam yola çevrilir
+            let source = if source.starts_with("http://") || source.starts_with("https://") {
+                source
+            } else {
+                let path = source.strip_prefix("file://").unwrap_or(&source);
+                let path = fs::canonicalize(path).with_context(|| format!("{} bulunamadı", path))?;
+                format!("file://{}", path.display())
+            };
+            run(Commands::Subscribe { name: name.clone(), source, profile, windows, days, refresh })?;
+
+            // Daemon yoksa liste hemen indirilip uygulanır
+            if !control::daemon_running() {
+                let mut config = load_config()?;
+                let subscription = config.subscriptions.iter().find(|s| s.name == name).context("Abonelik kaydedilemedi")?;
+                let diff = subscriptions::refresh(subscription)?;
+                println!("{} site indirildi.", diff.added.len());
+                config.load_lists();
+                let _ = apply_blocking(&config, &mut blocker::Blockers::new(false));
+            }
+        }
+
+        Commands::Export { format, profile, output } => {
+            let config = load_config()?;
+            let mut domains: Vec<String> = match &profile {
+                Some(name) => {
+                    let profile = config.profiles.iter().find(|p| p.name == *name).with_context(|| format!("{} isimli profil yok", name))?;
+                    profile.domains.clone()
+                }
+                None => config
+                    .rules
+                    .iter()
+                    .map(|r| r.domain.clone())
+                    .chain(config.profiles.iter().flat_map(|p| p.domains.iter().cloned()))
+                    .collect(),
+            };
+            domains.sort();
+            domains.dedup();
+
+            let content = lists::render(&domains, format, |domain| hosts_names(&config, domain));
+            match output {
+                Some(path) => {
+                    fs::write(&path, content).with_context(|| format!("{} yazılamadı", path))?;
+                    println!("{} site {} dosyasına yazıldı.", domains.len(), path);
                 }
-                thread::sleep(Duration::from_secs(10));
+                None => print!("{}", content),
             }
         }
+
+        command => run(command)?,
     }
     Ok(())
 }
+
+/// Komutu daemon'a gönderir, daemon yoksa doğrudan uygular
+fn run(command: Commands) -> Result<()> {
+    let output = match control::send(&command) {
+        Some(output) => output?,
+        None => execute_local(command)?,
+    };
+    print!("{}", output);
+    Ok(())
+}
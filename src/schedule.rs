@@ -0,0 +1,60 @@
+// Daemon zamanlayıcısı: engellemenin değişebileceği bir sonraki anı hesaplar ve o ana kadar
+// (veya bir config değişikliğiyle uyandırılana kadar) bekler.
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::{Config, WEEK, session_phase};
+
+static WOKEN: Mutex<bool> = Mutex::new(false);
+static WAKE: Condvar = Condvar::new();
+
+/// Bekleyen zamanlayıcıyı hemen uyandırır (config değişti, sonraki geçiş yeniden hesaplanmalı)
+pub fn wake() {
+    *WOKEN.lock().unwrap() = true;
+    WAKE.notify_all();
+}
+
+/// En fazla `timeout` kadar bekler; wake() ile uyandırıldıysa true döner
+pub fn wait(timeout: Duration) -> bool {
+    let woken = WOKEN.lock().unwrap();
+    let (mut woken, _) = WAKE.wait_timeout_while(woken, timeout, |woken| !*woken).unwrap();
+    std::mem::replace(&mut *woken, false)
+}
+
+/// Kural, profil, izin listesi, abonelik, ekran kuralı, istisna, duraklatma ve oturum sınırlarından `now`dan sonraki ilki
+pub fn next_transition(config: &Config, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let mut candidates = Vec::new();
+
+    for rule in config.rules.iter().filter(|r| r.enabled) {
+        candidates.extend(rule.window().ok().and_then(|w| w.next_boundary(&rule.days, now)));
+    }
+    for profile in config.profiles.iter().filter(|p| p.enabled) {
+        for window in &profile.windows {
+            candidates.extend(window.window().ok().and_then(|w| w.next_boundary(&window.days, now)));
+        }
+    }
+    candidates.extend(config.state.exceptions.values().copied());
+    candidates.extend(config.state.paused.values().copied());
+    for allowlist in config.allowlists.iter().filter(|a| a.enabled) {
+        for window in &allowlist.windows {
+            candidates.extend(window.window().ok().and_then(|w| w.next_boundary(&window.days, now)));
+        }
+    }
+    for subscription in &config.subscriptions {
+        for window in &subscription.windows {
+            candidates.extend(window.window().ok().and_then(|w| w.next_boundary(&window.days, now)));
+        }
+    }
+    for rule in config.bw_rules.iter().filter(|r| r.enabled) {
+        candidates.extend(rule.window().ok().and_then(|w| w.next_boundary(&WEEK, now)));
+    }
+    if let Some(session) = &config.state.session {
+        candidates.push(session_phase(session, now).1);
+    }
+    candidates.extend(config.state.lock_until);
+
+    candidates.into_iter().filter(|t| *t > now).min()
+}
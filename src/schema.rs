@@ -0,0 +1,364 @@
+// Config dosyasının sürümü, göçleri ve doğrulanması.
+// Dosya (TOML veya eski JSON) önce JSON değeri olarak okunur ve sırayla göçlerden geçirilerek güncel sürüme yükseltilir;
+// ancak ondan sonra Config'e çevrilir. Sürüm alanı olmayan dosyalar 0. sürüm sayılır.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use crate::{Backend, Config, Profile, Rule, WEEK, domain, subscriptions};
+
+pub const CURRENT_VERSION: u64 = 3;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// MIGRATIONS[n]: n. sürümden n+1. sürüme
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3];
+
+/// 0 -> 1: backend listesi, kural günleri ve istisna limiti açıkça yazılır
+fn v0_to_v1(config: &mut Map<String, Value>) {
+    // Tekil "backend" alanı "backends" listesi oldu
+    if let Some(backend) = config.remove("backend") {
+        let backends = if backend.is_array() { backend } else { json!([backend]) };
+        config.entry("backends").or_insert(backends);
+    }
+
+    // Gün alanı olmayan kurallar ve aralıklar her gün geçerliydi
+    fill_days(config);
+
+    // Alan yoksa limit 0 okunuyordu, davranış korunur
+    config.entry("exception_daily_limit").or_insert(json!(0));
+}
+
+/// 1 -> 2: sayaçlar, istisnalar, oturum ve kilit "state" altına taşınır (ayrı dosyaya yazılmak üzere)
+fn v1_to_v2(config: &mut Map<String, Value>) {
+    let mut state = Map::new();
+    for key in ["manual_bw_active", "exceptions_used_count", "last_exception_date", "session", "lock_until"] {
+        if let Some(value) = config.remove(key) {
+            state.insert(key.to_string(), value);
+        }
+    }
+
+    // Kural ve profil istisnaları domain başına tek bitiş anında birleşir (geç olan kazanır)
+    let mut exceptions: BTreeMap<String, Value> = BTreeMap::new();
+    let mut add = |domain: &str, until: Value| {
+        let instant = |value: &Value| value.as_str().and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+        let Some(new) = instant(&until) else { return };
+        if exceptions.get(domain).and_then(instant).is_none_or(|current| current < new) {
+            exceptions.insert(domain.to_string(), until);
+        }
+    };
+    if let Some(Value::Array(rules)) = config.get_mut("rules") {
+        for rule in rules.iter_mut().filter_map(Value::as_object_mut) {
+            let until = rule.remove("exception_until").unwrap_or(Value::Null);
+            if let Some(domain) = rule.get("domain").and_then(Value::as_str) {
+                add(domain, until);
+            }
+        }
+    }
+    if let Some(Value::Array(profiles)) = config.get_mut("profiles") {
+        for profile in profiles.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(Value::Object(profile_exceptions)) = profile.remove("exceptions") {
+                for (domain, until) in profile_exceptions {
+                    add(&domain, until);
+                }
+            }
+        }
+    }
+    state.insert("exceptions".to_string(), json!(exceptions));
+    config.insert("state".to_string(), Value::Object(state));
+}
+
+/// 2 -> 3: site ve ekran kurallarına ortak sırayla kimlik verilir, kurallar etkin olarak işaretlenir,
+/// kimlik sayacı kaydedilir
+fn v2_to_v3(config: &mut Map<String, Value>) {
+    let mut id = 0;
+    for key in ["rules", "bw_rules"] {
+        if let Some(Value::Array(rules)) = config.get_mut(key) {
+            for rule in rules.iter_mut().filter_map(Value::as_object_mut) {
+                id += 1;
+                rule.insert("id".to_string(), json!(id));
+                rule.entry("enabled").or_insert(json!(true));
+            }
+        }
+    }
+    config.insert("next_id".to_string(), json!(id + 1));
+}
+
+/// Gün alanı olmayan kurallar ve profil aralıkları her gün geçerlidir
+fn fill_days(config: &mut Map<String, Value>) {
+    let week = json!(WEEK);
+    let add_days = |item: &mut Value| {
+        if let Some(item) = item.as_object_mut() {
+            item.entry("days").or_insert(week.clone());
+        }
+    };
+    if let Some(Value::Array(rules)) = config.get_mut("rules") {
+        rules.iter_mut().for_each(add_days);
+    }
+    if let Some(Value::Array(profiles)) = config.get_mut("profiles") {
+        for profile in profiles.iter_mut() {
+            if let Some(Value::Array(windows)) = profile.get_mut("windows") {
+                windows.iter_mut().for_each(add_days);
+            }
+        }
+    }
+}
+
+/// Değeri güncel sürüme yükseltir; yükseltildiyse eski sürümü döner
+fn migrate(value: &mut Value) -> Result<Option<u64>> {
+    let config = value.as_object_mut().context("Config bir JSON nesnesi olmalı")?;
+    let version = match config.get("version") {
+        None => 0,
+        Some(v) => v.as_u64().context("version alanı pozitif bir sayı olmalı")?,
+    };
+    if version > CURRENT_VERSION {
+        anyhow::bail!("Config {}. sürümde, bu focus en fazla {}. sürümü okuyabilir", version, CURRENT_VERSION);
+    }
+    if version == CURRENT_VERSION {
+        return Ok(None);
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(config);
+    }
+    config.insert("version".to_string(), json!(CURRENT_VERSION));
+    Ok(Some(version))
+}
+
+/// Okunan değeri Config'e çevirir; eski sürümse yükseltilmiş config ile birlikte eski sürümü de döner
+pub fn parse(mut value: Value) -> Result<(Config, Option<u64>)> {
+    let migrated_from = migrate(&mut value)?;
+    let config = serde_json::from_value(value).context("Config biçimi hatalı")?;
+    Ok((config, migrated_from))
+}
+
+/// conf.d altındaki bir dosya: yalnızca kural, profil ve alt alan adı içerebilir
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fragment {
+    pub rules: Vec<Rule>,
+    pub profiles: Vec<Profile>,
+    pub subdomains: BTreeMap<String, Vec<String>>,
+}
+
+/// conf.d dosyasını okur; gün belirtilmeyen kurallar her gün geçerlidir
+pub fn parse_fragment(mut value: Value) -> Result<Fragment> {
+    let fragment = value.as_object_mut().context("Dosya bir tablo olmalı")?;
+    fill_days(fragment);
+    let fragment: Fragment = serde_json::from_value(value).context("Dosya biçimi hatalı")?;
+    anyhow::ensure!(
+        fragment.profiles.iter().all(|p| !p.name.is_empty()),
+        "Profil isimleri boş olamaz"
+    );
+    Ok(fragment)
+}
+
+/// Yükseltmeden önce eski dosyanın kopyası (örn: config.json.v0.bak)
+pub fn backup(path: &str, version: u64) -> Result<String> {
+    let backup = format!("{}.v{}.bak", path, version);
+    fs::copy(path, &backup).with_context(|| format!("{} yedeklenemedi", path))?;
+    Ok(backup)
+}
+
+/// Dosyada olup Config'e karşılık gelmeyen alanlar (yazım hataları vb.)
+fn unknown_fields(file: &Value, known: &Value, path: &str, found: &mut Vec<String>) {
+    match (file, known) {
+        (Value::Object(file), Value::Object(known)) => {
+            for (key, value) in file {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match known.get(key) {
+                    Some(known) => unknown_fields(value, known, &child, found),
+                    None => found.push(child),
+                }
+            }
+        }
+        (Value::Array(file), Value::Array(known)) => {
+            for (index, (value, known)) in file.iter().zip(known).enumerate() {
+                unknown_fields(value, known, &format!("{}[{}]", path, index), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Config içeriğindeki sorunların listesi (boşsa geçerli)
+pub fn validate(mut value: Value) -> Vec<String> {
+    if let Err(e) = migrate(&mut value) {
+        return vec![format!("{:#}", e)];
+    }
+    let config: Config = match serde_json::from_value(value.clone()) {
+        Ok(config) => config,
+        Err(e) => return vec![format!("Config biçimi hatalı: {}", e)],
+    };
+
+    let mut problems = Vec::new();
+
+    // Eski sürümlerden taşınan durum ayrı dosyaya yazılır, config alanı sayılmaz
+    if let Some(config) = value.as_object_mut() {
+        config.remove("state");
+    }
+    let mut unknown = Vec::new();
+    if let Ok(known) = serde_json::to_value(&config) {
+        unknown_fields(&value, &known, "", &mut unknown);
+    }
+    problems.extend(unknown.into_iter().map(|field| format!("bilinmeyen alan: {}", field)));
+
+    check_items(&config.rules, &config.profiles, &mut problems);
+    check_subdomains(&config.subdomains, &mut problems);
+
+    // Kimliksiz kurallara yüklenirken kimlik verilir; çakışanlar ise focus rule ile karışır
+    let mut ids = HashSet::new();
+    let rule_ids = config.rules.iter().map(|r| ("rules", r.id));
+    for (field, id) in rule_ids.chain(config.bw_rules.iter().map(|r| ("bw_rules", r.id))) {
+        if id != 0 && !ids.insert(id) {
+            problems.push(format!("{}: {} kimliği birden fazla kuralda kullanılmış", field, id));
+        }
+        if id == u32::MAX {
+            problems.push(format!("{}: {} kimliği çok büyük, yeni kurallara kimlik verilemez", field, id));
+        }
+    }
+    if config.next_id == u32::MAX {
+        problems.push(format!("next_id: {} çok büyük, yeni kurallara kimlik verilemez", config.next_id));
+    }
+
+    for (index, rule) in config.bw_rules.iter().enumerate() {
+        if let Err(e) = rule.window() {
+            problems.push(format!("bw_rules[{}]: {:#}", index, e));
+        }
+    }
+
+    let mut names = HashSet::new();
+    for (index, allowlist) in config.allowlists.iter().enumerate() {
+        if !names.insert(&allowlist.name) {
+            problems.push(format!("allowlists[{}]: {} isimli izin listesi birden fazla kez tanımlı", index, allowlist.name));
+        }
+        for (d, name) in allowlist.domains.iter().enumerate() {
+            check_domain(name, &format!("allowlists[{}].domains[{}] ({})", index, d, allowlist.name), &mut problems);
+        }
+        if allowlist.windows.is_empty() {
+            problems.push(format!("allowlists[{}] ({}): saat aralığı yok", index, allowlist.name));
+        }
+        for (w, window) in allowlist.windows.iter().enumerate() {
+            if let Err(e) = window.window() {
+                problems.push(format!("allowlists[{}].windows[{}] ({}): {:#}", index, w, allowlist.name, e));
+            }
+        }
+    }
+    if !config.allowlists.is_empty() && !config.backends.contains(&Backend::Dns) {
+        problems.push("allowlists: izin listeleri yalnızca dns backend'i ile uygulanır (backends listesine \"dns\" ekleyin)".to_string());
+    }
+
+    let mut names = HashSet::new();
+    for (index, subscription) in config.subscriptions.iter().enumerate() {
+        if !names.insert(&subscription.name) {
+            problems.push(format!("subscriptions[{}]: {} isimli abonelik birden fazla kez tanımlı", index, subscription.name));
+        }
+        if !subscriptions::valid_name(&subscription.name) {
+            problems.push(format!("subscriptions[{}]: ad yalnızca harf, rakam, - ve _ içerebilir: {}", index, subscription.name));
+        }
+        if let Some(profile) = &subscription.profile
+            && !config.profiles.iter().any(|p| p.name == *profile)
+        {
+            problems.push(format!("subscriptions[{}] ({}): {} isimli profil yok", index, subscription.name, profile));
+        }
+        if subscription.profile.is_none() && subscription.windows.is_empty() {
+            problems.push(format!("subscriptions[{}] ({}): profil veya saat aralığı yok", index, subscription.name));
+        }
+        for (w, window) in subscription.windows.iter().enumerate() {
+            if let Err(e) = window.window() {
+                problems.push(format!("subscriptions[{}].windows[{}] ({}): {:#}", index, w, subscription.name, e));
+            }
+        }
+        if subscription.refresh_minutes <= 0 {
+            problems.push(format!("subscriptions[{}] ({}): yenileme aralığı pozitif olmalı", index, subscription.name));
+        }
+    }
+
+    for (field, address) in [("dns.listen", &config.dns.listen), ("dns.upstream", &config.dns.upstream)] {
+        if address.parse::<SocketAddr>().is_err() {
+            problems.push(format!("{}: adres hatalı: {}", field, address));
+        }
+    }
+    problems
+}
+
+/// conf.d dosyasındaki sorunların listesi
+pub fn validate_fragment(value: Value) -> Vec<String> {
+    match parse_fragment(value) {
+        Ok(fragment) => {
+            let mut problems = Vec::new();
+            check_items(&fragment.rules, &fragment.profiles, &mut problems);
+            check_subdomains(&fragment.subdomains, &mut problems);
+            problems
+        }
+        Err(e) => vec![format!("{:#}", e)],
+    }
+}
+
+/// Elle yazılmış domainin focus add'in saklayacağı biçimde olup olmadığı
+fn check_domain(name: &str, field: &str, problems: &mut Vec<String>) {
+    match domain::normalize(name) {
+        Ok(normalized) if normalized != name => {
+            problems.push(format!("{}: {} yerine {} yazılmalı", field, name, normalized));
+        }
+        Ok(_) => {}
+        Err(e) => problems.push(format!("{}: {:#}", field, e)),
+    }
+}
+
+/// Alt alan adları hosts dosyasına yazıldığı için geçerli etiketler olmalı
+fn check_subdomains(subdomains: &BTreeMap<String, Vec<String>>, problems: &mut Vec<String>) {
+    for (base, prefixes) in subdomains {
+        check_domain(base, &format!("subdomains.{}", base), problems);
+        for prefix in prefixes {
+            match domain::subdomain(prefix, base) {
+                Ok(normalized) if normalized != *prefix => {
+                    problems.push(format!("subdomains.{}: {} yerine {} yazılmalı", base, prefix, normalized));
+                }
+                Ok(_) => {}
+                Err(e) => problems.push(format!("subdomains.{}: {:#}", base, e)),
+            }
+        }
+    }
+}
+
+fn check_items(rules: &[Rule], profiles: &[Profile], problems: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    for (index, rule) in rules.iter().enumerate() {
+        check_domain(&rule.domain, &format!("rules[{}]", index), problems);
+        if let Err(e) = rule.window() {
+            problems.push(format!("rules[{}] ({}): {:#}", index, rule.domain, e));
+        }
+        if rule.days.is_empty() {
+            problems.push(format!("rules[{}] ({}): hiç gün seçilmemiş", index, rule.domain));
+        }
+        let key = (&rule.domain, &rule.start_time, &rule.end_time, &rule.days);
+        if !seen.insert(key) {
+            problems.push(format!("rules[{}] ({}): aynı kural birden fazla kez tanımlı", index, rule.domain));
+        }
+    }
+
+    let mut names = HashSet::new();
+    for (index, profile) in profiles.iter().enumerate() {
+        if !names.insert(&profile.name) {
+            problems.push(format!("profiles[{}]: {} isimli profil birden fazla kez tanımlı", index, profile.name));
+        }
+        if profile.windows.is_empty() {
+            problems.push(format!("profiles[{}] ({}): saat aralığı yok", index, profile.name));
+        }
+        for (d, name) in profile.domains.iter().enumerate() {
+            check_domain(name, &format!("profiles[{}].domains[{}] ({})", index, d, profile.name), problems);
+        }
+        for (w, window) in profile.windows.iter().enumerate() {
+            if let Err(e) = window.window() {
+                problems.push(format!("profiles[{}].windows[{}] ({}): {:#}", index, w, profile.name, e));
+            }
+        }
+    }
+}
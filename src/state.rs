@@ -0,0 +1,98 @@
+// Daemon'un yönettiği çalışma durumu: istisna bitişleri, günlük istisna sayacı, manuel ekran
+// modu, oturum ve kilit. Kullanıcının yazdığı kurallardan ayrı dosyada tutulur; böylece config
+// salt okunur olabilir, sürüm kontrolünde tutulabilir ve yapılandırma yönetimiyle dağıtılabilir.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{Session, hosts};
+
+pub const STATE_PATH: &str = "/var/lib/focus/state.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct State {
+    pub manual_bw_active: bool,
+    pub exceptions_used_count: u32,
+    pub last_exception_date: String,
+    /// Domain başına istisna bitişi (kurallar ve profiller için ortak)
+    pub exceptions: BTreeMap<String, DateTime<Local>>,
+    /// Kural kimliği başına duraklatma bitişi (focus rule pause --until/--for)
+    pub paused: BTreeMap<u32, DateTime<Local>>,
+    pub session: Option<Session>,
+    pub lock_until: Option<DateTime<Local>>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            manual_bw_active: false,
+            exceptions_used_count: 0,
+            last_exception_date: "1970-01-01".to_string(),
+            exceptions: BTreeMap::new(),
+            paused: BTreeMap::new(),
+            session: None,
+            lock_until: None,
+        }
+    }
+}
+
+fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+impl State {
+    /// Domain için `now` anında geçerli istisnanın bitişi
+    pub fn exception(&self, domain: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.exceptions.get(domain).copied().filter(|until| *until > now)
+    }
+
+    /// Kural `now` anında duraklatılmışsa duraklatmanın bitişi
+    pub fn paused(&self, id: u32, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.paused.get(&id).copied().filter(|until| *until > now)
+    }
+
+    /// Gün değiştiyse sayacı sıfırlar ve bugün için istisna hakkı kalıp kalmadığını döner
+    pub fn exception_available(&mut self, daily_limit: u32) -> bool {
+        let today = today();
+        if self.last_exception_date != today {
+            self.exceptions_used_count = 0;
+            self.last_exception_date = today;
+        }
+        self.exceptions_used_count < daily_limit
+    }
+
+    /// Süresi dolmuş istisnaları, duraklatmaları ve kilidi siler; bir şey silindiyse true döner
+    pub fn prune(&mut self, now: DateTime<Local>) -> bool {
+        let before = self.exceptions.len() + self.paused.len();
+        self.exceptions.retain(|_, until| *until > now);
+        self.paused.retain(|_, until| *until > now);
+        let mut changed = self.exceptions.len() + self.paused.len() != before;
+        if self.lock_until.is_some_and(|until| until <= now) {
+            self.lock_until = None;
+            changed = true;
+        }
+        changed
+    }
+}
+
+pub fn load() -> Result<State> {
+    match fs::read_to_string(STATE_PATH) {
+        Ok(content) => serde_json::from_str(&content).context("Durum dosyası okunamadı"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(State::default()),
+        Err(e) => Err(e).context("Durum dosyası okunamadı"),
+    }
+}
+
+pub fn save(state: &State) -> Result<()> {
+    let path = Path::new(STATE_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    hosts::write_atomic(path, &serde_json::to_string_pretty(state)?).context("Durum dosyası yazılamadı")
+}
@@ -0,0 +1,148 @@
+// Uzak veya yerel engel listesi abonelikleri. Listeler daemon tarafından aralıklarla indirilip
+// /var/lib/focus/lists altında satır başına bir domain olarak saklanır; engelleme her zaman bu
+// önbellekten yapılır, böylece kaynak erişilemez olsa da son indirilen liste geçerli kalır.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+
+use crate::{Subscription, hosts, lists};
+
+pub const LISTS_DIR: &str = "/var/lib/focus/lists";
+const FETCH_TIMEOUT_SECS: &str = "60";
+
+/// Yenilemede önbelleğe göre eklenen ve çıkarılan domainler
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Kaynakta anlaşılamayan satır sayısı
+    pub skipped: usize,
+}
+
+/// Ad önbellek dosyasının adı olarak kullanıldığı için yalnızca harf, rakam, - ve _ içerebilir
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn cache_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.list", name))
+}
+
+/// Önbellekteki domainler (liste henüz indirilmediyse boş). Önbellek de liste ayrıştırıcısından
+/// geçer; eski sürümlerin yazdığı veya elle değiştirilmiş satırlar kurallardaki biçime çevrilir.
+pub fn cached(name: &str) -> Vec<String> {
+    fs::read_to_string(cache_path(Path::new(LISTS_DIR), name))
+        .map(|content| lists::parse(&content).domains)
+        .unwrap_or_default()
+}
+
+/// Listenin son başarılı indirilme zamanı
+pub fn updated_at(name: &str) -> Option<SystemTime> {
+    fs::metadata(cache_path(Path::new(LISTS_DIR), name)).and_then(|m| m.modified()).ok()
+}
+
+/// Önbellek yoksa veya yenileme aralığı dolduysa true
+pub fn due(subscription: &Subscription, now: SystemTime) -> bool {
+    let interval = Duration::from_secs(subscription.refresh_minutes.max(1) as u64 * 60);
+    updated_at(&subscription.name).is_none_or(|updated| now.duration_since(updated).unwrap_or_default() >= interval)
+}
+
+/// Kaynağın içeriği: http(s) adresleri curl ile, file:// ve dosya yolları doğrudan okunur
+fn fetch(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let output = Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location", "--max-time", FETCH_TIMEOUT_SECS, source])
+            .output()
+            .context("curl çalıştırılamadı")?;
+        if !output.status.success() {
+            anyhow::bail!("{} indirilemedi: {}", source, String::from_utf8_lossy(&output.stderr).trim());
+        }
+        return String::from_utf8(output.stdout).with_context(|| format!("{} metin değil", source));
+    }
+    let path = source.strip_prefix("file://").unwrap_or(source);
+    fs::read_to_string(path).with_context(|| format!("{} okunamadı", path))
+}
+
+/// Listeyi kaynağından indirip önbelleği günceller
+pub fn refresh(subscription: &Subscription) -> Result<Diff> {
+    refresh_in(Path::new(LISTS_DIR), subscription)
+}
+
+fn refresh_in(dir: &Path, subscription: &Subscription) -> Result<Diff> {
+    anyhow::ensure!(valid_name(&subscription.name), "Geçersiz abonelik adı: {}", subscription.name);
+    let parsed = lists::parse(&fetch(&subscription.source)?);
+    if parsed.domains.is_empty() {
+        // Boş veya tamamen hatalı bir yanıt eldeki listeyi silmemeli
+        anyhow::bail!("{} içinde domain bulunamadı", subscription.source);
+    }
+
+    let path = cache_path(dir, &subscription.name);
+    let previous = fs::read_to_string(&path).map(|content| lists::parse(&content).domains).unwrap_or_default();
+    let (old, new): (HashSet<&String>, HashSet<&String>) = (previous.iter().collect(), parsed.domains.iter().collect());
+    let diff = Diff {
+        added: parsed.domains.iter().filter(|d| !old.contains(d)).cloned().collect(),
+        removed: previous.iter().filter(|d| !new.contains(d)).cloned().collect(),
+        skipped: parsed.skipped.len(),
+    };
+
+    fs::create_dir_all(dir).with_context(|| format!("{} oluşturulamadı", dir.display()))?;
+    let mut content = parsed.domains.join("\n");
+    content.push('\n');
+    hosts::write_atomic(&path, &content)?;
+    Ok(diff)
+}
+
+/// Artık config'te olmayan aboneliklerin önbelleğini siler
+pub fn remove_cache(name: &str) {
+    let _ = fs::remove_file(cache_path(Path::new(LISTS_DIR), name));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_reports_changes_and_keeps_cache_on_empty_source() {
+        let dir = std::env::temp_dir().join(format!("focus-lists-test-{}", std::process::id()));
+        let source = dir.join("source.txt");
+        fs::create_dir_all(&dir).unwrap();
+        let subscription = Subscription {
+            name: "ads".to_string(),
+            source: format!("file://{}", source.display()),
+            profile: None,
+            windows: vec![],
+            refresh_minutes: 60,
+            domains: vec![],
+        };
+        let cache = || fs::read_to_string(cache_path(&dir, "ads")).unwrap();
+
+        fs::write(&source, "example.org\n0.0.0.0 www.ads.net\n! yorum\n@@||allowed.com^\n").unwrap();
+        let diff = refresh_in(&dir, &subscription).unwrap();
+        assert_eq!(diff.added, ["ads.net", "example.org"]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.skipped, 1);
+        assert_eq!(cache(), "ads.net\nexample.org\n");
+
+        fs::write(&source, "example.org\n||tracker.io^\n").unwrap();
+        let diff = refresh_in(&dir, &subscription).unwrap();
+        assert_eq!(diff.added, ["*.tracker.io"]);
+        assert_eq!(diff.removed, ["ads.net"]);
+        assert_eq!(diff.skipped, 0);
+
+        // Boş yanıt eldeki listeyi silmez
+        fs::write(&source, "# boş\n").unwrap();
+        assert!(refresh_in(&dir, &subscription).is_err());
+        assert_eq!(cache(), "*.tracker.io\nexample.org\n");
+
+        fs::remove_file(&source).unwrap();
+        assert!(refresh_in(&dir, &subscription).is_err());
+        let invalid = Subscription { name: "../ads".to_string(), ..subscription };
+        assert!(refresh_in(&dir, &invalid).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+// hosts ve config dosyalarının inotify ile izlenmesi ve kurcalama kayıtları.
+// Dosyalar atomik yazmada rename ile değiştirildiği için dosyanın kendisi değil bulunduğu dizin izlenir.
+// Dizinler (conf.d) için içindeki herhangi bir dosyanın değişmesi dizinin değişmesi sayılır.
+
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::fd::FromRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{Context, Result};
+use chrono::Local;
+
+use crate::DaemonState;
+
+pub const TAMPER_LOG: &str = "/var/lib/focus/tamper.log";
+
+// struct inotify_event: wd, mask, cookie, len (+ len bayt isim)
+const EVENT_HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+/// Verilen dosyaları ve dizinleri izler; biri değiştiğinde daemon durumuna bildirir
+pub fn start(files: &[&str], dirs: &[&str], state: Arc<Mutex<DaemonState>>) -> Result<()> {
+    // SAFETY: parametresiz sistem çağrısı, dönen fd aşağıda File'a devredilir
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error()).context("inotify başlatılamadı");
+    }
+    // SAFETY: fd az önce açıldı ve başka bir yerde kullanılmıyor
+    let mut events = unsafe { File::from_raw_fd(fd) };
+
+    // (wd, bildirilecek yol, dizin mi)
+    let mut watches: Vec<(i32, PathBuf, bool)> = Vec::new();
+    let targets = files.iter().map(|f| (*f, false)).chain(dirs.iter().map(|d| (*d, true)));
+    for (path, is_dir) in targets {
+        let path = PathBuf::from(path);
+        let dir = if is_dir { path.as_path() } else { path.parent().context("Geçersiz dosya yolu")? };
+        fs::create_dir_all(dir)?;
+        let dir_name = CString::new(dir.as_os_str().as_encoded_bytes())?;
+        // SAFETY: fd geçerli, dir_name NUL ile biten bir C dizgisi
+        let wd = unsafe {
+            libc::inotify_add_watch(
+                fd,
+                dir_name.as_ptr(),
+                libc::IN_CLOSE_WRITE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO | libc::IN_DELETE,
+            )
+        };
+        if wd < 0 {
+            return Err(std::io::Error::last_os_error()).with_context(|| format!("{} izlenemiyor", dir.display()));
+        }
+        watches.push((wd, path, is_dir));
+    }
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            let len = match events.read(&mut buf) {
+                Ok(len) => len,
+                Err(e) => {
+                    eprintln!("inotify Hatası: {}", e);
+                    return;
+                }
+            };
+
+            let mut changed: Vec<&Path> = Vec::new();
+            let mut pos = 0;
+            while pos + EVENT_HEADER <= len {
+                let wd = i32::from_ne_bytes(buf[pos..pos + 4].try_into().unwrap());
+                let name_len = u32::from_ne_bytes(buf[pos + 12..pos + 16].try_into().unwrap()) as usize;
+                let name = &buf[pos + EVENT_HEADER..(pos + EVENT_HEADER + name_len).min(len)];
+                let name = name.split(|&b| b == 0).next().unwrap_or_default();
+                pos += EVENT_HEADER + name_len;
+
+                // Dizinlerde atomik yazmanın geçici dosyaları (.isim.focus-tmp) sayılmaz
+                let watched = watches.iter().find(|(w, path, is_dir)| {
+                    *w == wd
+                        && if *is_dir {
+                            !name.is_empty() && !name.starts_with(b".")
+                        } else {
+                            path.file_name().is_some_and(|f| f.as_encoded_bytes() == name)
+                        }
+                });
+                if let Some((_, path, _)) = watched
+                    && !changed.contains(&path.as_path())
+                {
+                    changed.push(path);
+                }
+            }
+
+            for path in changed {
+                state.lock().unwrap().file_changed(path);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Kurcalama olayını zaman damgasıyla kaydeder
+pub fn record_tamper(message: &str) {
+    let line = format!("{} {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+    eprint!("Kurcalama: {}", line);
+
+    let result = (|| -> Result<()> {
+        if let Some(parent) = Path::new(TAMPER_LOG).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(TAMPER_LOG)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        eprintln!("Kurcalama kaydı yazılamadı: {}", e);
+    }
+}
+
+/// Son kurcalama kayıtları (eskiden yeniye)
+pub fn recent_tampers(count: usize) -> Vec<String> {
+    let content = fs::read_to_string(TAMPER_LOG).unwrap_or_default();
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
@@ -0,0 +1,270 @@
+// Tüm kural türlerinin ortak zaman aralığı tipi.
+// Aralıklar yarı açıktır: 09:00-17:00 saat 17:00:00'da biter. Başlangıç ile bitiş aynıysa
+// (örn: 00:00-00:00) aralık tüm gündür. Gece yarısını aşan aralıklarda (22:00-02:00) gece
+// yarısından sonraki kısım aralığın başladığı güne aittir.
+// Her günün aralığı gerçek zamanda tek parçadır: duvar saatinin başlangıca ilk ulaştığı andan
+// bitişe ilk ulaştığı ana kadar. Yaz saati geçişinde var olmayan saatler boşluktan sonraki ilk
+// ana kayar, iki kez yaşanan saatlerin ilki alınır.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Days, Local, LocalResult, NaiveDate, NaiveTime, Timelike, Weekday};
+
+const DAY_SECONDS: u32 = 86_400;
+const WEEK_SECONDS: u32 = 7 * DAY_SECONDS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// "HH:MM" veya "HH:MM:SS"; bitiş için "24:00" gece yarısı demektir
+pub fn parse_time(input: &str) -> Result<NaiveTime> {
+    let input = input.trim();
+    if input == "24:00" || input == "24:00:00" {
+        return Ok(NaiveTime::MIN);
+    }
+    NaiveTime::parse_from_str(input, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(input, "%H:%M"))
+        .with_context(|| format!("Saat formatı hatalı: {} (örn: 09:00 veya 09:00:30)", input))
+}
+
+/// Yerel tarih ve saatin karşılık geldiği an
+fn local_instant(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
+    let mut naive = date.and_time(time);
+    // Yaz saatine geçişte atlanan saatler yerine boşluktan sonraki ilk an
+    for _ in 0..=180 {
+        match naive.and_local_timezone(Local) {
+            LocalResult::Single(instant) => return Some(instant),
+            // Sıra ofsete göre olabildiği için earliest() her zaman ilk yaşanan an değildir
+            LocalResult::Ambiguous(a, b) => return Some(a.min(b)),
+            LocalResult::None => naive += chrono::Duration::minutes(1),
+        }
+    }
+    None
+}
+
+impl Window {
+    pub fn parse(start: &str, end: &str) -> Result<Self> {
+        Ok(Self { start: parse_time(start)?, end: parse_time(end)? })
+    }
+
+    pub fn is_all_day(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// `date` gününe ait aralığın gerçek zamandaki [başlangıç, bitiş) anları
+    fn span(&self, date: NaiveDate) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let end_date = if self.end <= self.start { date.checked_add_days(Days::new(1))? } else { date };
+        Some((local_instant(date, self.start)?, local_instant(end_date, self.end)?))
+    }
+
+    /// Dünden yarına, aralığın seçili günlere ait parçaları
+    fn spans(&self, days: &[Weekday], now: DateTime<Local>) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        let today = now.date_naive();
+        [today.pred_opt(), Some(today), today.succ_opt()]
+            .into_iter()
+            .flatten()
+            .filter(|date| days.contains(&date.weekday()))
+            .filter_map(|date| self.span(date))
+            .collect()
+    }
+
+    /// Aralık `now` anında etkin mi?
+    pub fn contains(&self, days: &[Weekday], now: DateTime<Local>) -> bool {
+        self.spans(days, now).iter().any(|(start, end)| *start <= now && now < *end)
+    }
+
+    /// `now`dan sonraki ilk başlangıç veya bitiş anı
+    pub fn next_boundary(&self, days: &[Weekday], now: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.spans(days, now)
+            .into_iter()
+            .flat_map(|(start, end)| [start, end])
+            .filter(|t| *t > now)
+            .min()
+    }
+
+    /// Haftanın başından (pazartesi 00:00) itibaren saniye cinsinden kapsanan [başlangıç, bitiş) parçaları
+    fn week_intervals(&self, days: &[Weekday]) -> Vec<(u32, u32)> {
+        let start = self.start.num_seconds_from_midnight();
+        let length = if self.is_all_day() {
+            DAY_SECONDS
+        } else {
+            (self.end.num_seconds_from_midnight() + DAY_SECONDS - start) % DAY_SECONDS
+        };
+
+        let mut intervals = Vec::new();
+        for day in days {
+            let from = day.num_days_from_monday() * DAY_SECONDS + start;
+            let to = from + length;
+            if to <= WEEK_SECONDS {
+                intervals.push((from, to));
+            } else {
+                // Pazar gecesini aşan kısım haftanın başına sarar
+                intervals.push((from, WEEK_SECONDS));
+                intervals.push((0, to - WEEK_SECONDS));
+            }
+        }
+        intervals
+    }
+
+    pub fn describe(&self) -> String {
+        if self.is_all_day() {
+            return "tüm gün".to_string();
+        }
+        let format = |t: NaiveTime| {
+            if t.second() == 0 { t.format("%H:%M").to_string() } else { t.format("%H:%M:%S").to_string() }
+        };
+        format!("{}-{}", format(self.start), format(self.end))
+    }
+}
+
+/// Yeni aralıklar eskilerin kapsadığı her anı kapsıyor mu? (Kilitliyken daraltmayı önlemek için)
+pub fn covers(new: &[(Window, Vec<Weekday>)], old: &[(Window, Vec<Weekday>)]) -> bool {
+    let mut merged: Vec<(u32, u32)> = Vec::new();
+    let mut intervals: Vec<(u32, u32)> = new.iter().flat_map(|(w, days)| w.week_intervals(days)).collect();
+    intervals.sort();
+    for (from, to) in intervals {
+        match merged.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+
+    old.iter()
+        .flat_map(|(w, days)| w.week_intervals(days))
+        .all(|(from, to)| from == to || merged.iter().any(|(f, t)| *f <= from && to <= *t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WEEK;
+    use chrono::{NaiveDateTime, TimeZone, Utc};
+    use std::sync::Once;
+
+    fn window(start: &str, end: &str) -> Window {
+        Window::parse(start, end).unwrap()
+    }
+
+    fn at(input: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S").unwrap();
+        naive.and_local_timezone(Local).earliest().unwrap()
+    }
+
+    fn utc(input: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S").unwrap();
+        Utc.from_utc_datetime(&naive).with_timezone(&Local)
+    }
+
+    /// Yaz saati testleri Europe/Berlin'e göre yazıldı (2026: 29 Mart 02:00 -> 03:00, 25 Ekim 03:00 -> 02:00).
+    /// chrono yerel saat dilimini iş parçacığı başına ilk kullanımda okuduğu için her test kendi başında çağırır.
+    fn berlin() {
+        static TZ: Once = Once::new();
+        // SAFETY: testlerde ortamı okuyan C kodu çalışmıyor; değişken yalnızca bir kez yazılır
+        TZ.call_once(|| unsafe { std::env::set_var("TZ", "Europe/Berlin") });
+    }
+
+    #[test]
+    fn end_is_exclusive() {
+        let w = window("09:00", "17:00");
+        assert!(!w.contains(&WEEK, at("2026-03-11 08:59:59")));
+        assert!(w.contains(&WEEK, at("2026-03-11 09:00:00")));
+        assert!(w.contains(&WEEK, at("2026-03-11 16:59:59")));
+        assert!(!w.contains(&WEEK, at("2026-03-11 17:00:00")));
+        assert_eq!(w.next_boundary(&WEEK, at("2026-03-11 12:00:00")), Some(at("2026-03-11 17:00:00")));
+    }
+
+    #[test]
+    fn equal_start_and_end_is_all_day() {
+        for (start, end) in [("00:00", "00:00"), ("00:00", "24:00"), ("09:00", "09:00")] {
+            let w = window(start, end);
+            assert!(w.is_all_day());
+            assert_eq!(w.describe(), "tüm gün");
+            for time in ["00:00:00", "08:59:59", "09:00:00", "23:59:59"] {
+                assert!(w.contains(&WEEK, at(&format!("2026-03-11 {}", time))), "{}-{} {}", start, end, time);
+            }
+        }
+        // Yalnızca seçili günlerde
+        assert!(!window("00:00", "00:00").contains(&[Weekday::Mon], at("2026-03-11 12:00:00")));
+    }
+
+    #[test]
+    fn spanning_midnight_belongs_to_start_day() {
+        let w = window("22:00", "02:00");
+        let monday = [Weekday::Mon];
+        assert!(!w.contains(&monday, at("2026-03-09 01:00:00")));
+        assert!(w.contains(&monday, at("2026-03-09 22:00:00")));
+        assert!(w.contains(&monday, at("2026-03-10 01:59:59")));
+        assert!(!w.contains(&monday, at("2026-03-10 02:00:00")));
+        assert!(!w.contains(&monday, at("2026-03-10 23:00:00")));
+        assert_eq!(w.next_boundary(&monday, at("2026-03-09 12:00:00")), Some(at("2026-03-09 22:00:00")));
+        assert_eq!(w.next_boundary(&monday, at("2026-03-09 23:00:00")), Some(at("2026-03-10 02:00:00")));
+    }
+
+    #[test]
+    fn midnight_end_can_be_written_as_24_00() {
+        assert_eq!(parse_time("24:00").unwrap(), NaiveTime::MIN);
+        assert_eq!(parse_time("24:00:00").unwrap(), NaiveTime::MIN);
+        assert!(parse_time("24:30").is_err());
+        assert!(parse_time("25:00").is_err());
+        assert!(parse_time("9").is_err());
+
+        let w = window("18:00", "24:00");
+        assert_eq!(w, window("18:00", "00:00"));
+        assert!(w.contains(&WEEK, at("2026-03-11 23:59:59")));
+        assert!(!w.contains(&WEEK, at("2026-03-12 00:00:00")));
+    }
+
+    #[test]
+    fn covers_compares_whole_week() {
+        let weekdays = WEEK[..5].to_vec();
+        let old = [(window("09:00", "17:00"), vec![Weekday::Mon])];
+        assert!(covers(&[(window("08:00", "18:00"), weekdays.clone())], &old));
+        assert!(covers(&[(window("09:00", "12:00"), weekdays.clone()), (window("12:00", "17:00"), weekdays)], &old));
+        assert!(!covers(&[(window("10:00", "17:00"), vec![Weekday::Mon])], &old));
+        assert!(!covers(&[(window("09:00", "17:00"), vec![Weekday::Tue])], &old));
+        assert!(covers(&[], &[]));
+
+        // Pazar gecesi haftanın başına sarar
+        let sunday_night = [(window("22:00", "02:00"), vec![Weekday::Sun])];
+        let split = [(window("20:00", "24:00"), vec![Weekday::Sun]), (window("00:00", "03:00"), vec![Weekday::Mon])];
+        assert!(covers(&split, &sunday_night));
+        assert!(!covers(&split[..1], &sunday_night));
+
+        let all_day = [(window("00:00", "00:00"), vec![Weekday::Wed])];
+        assert!(!covers(&all_day, &[(window("23:00", "01:00"), vec![Weekday::Tue])]));
+        assert!(covers(&all_day, &[(window("01:00", "23:00"), vec![Weekday::Wed])]));
+        assert!(!covers(&[(window("00:00", "23:59"), vec![Weekday::Wed])], &all_day));
+    }
+
+    #[test]
+    fn skipped_hours_move_to_end_of_gap() {
+        berlin();
+        // 02:30 yoktur; aralık 03:00 CEST (01:00 UTC) anında başlar
+        let w = window("02:30", "04:00");
+        let sunday = [Weekday::Sun];
+        assert_eq!(w.next_boundary(&sunday, utc("2026-03-28 23:00:00")), Some(utc("2026-03-29 01:00:00")));
+        assert!(!w.contains(&sunday, utc("2026-03-29 00:59:59")));
+        assert!(w.contains(&sunday, utc("2026-03-29 01:00:00")));
+        assert!(!w.contains(&sunday, utc("2026-03-29 02:00:00")));
+    }
+
+    #[test]
+    fn repeated_hours_use_first_occurrence() {
+        berlin();
+        // 02:00-03:00 iki kez yaşanır: önce CEST (00:00-01:00 UTC), sonra CET (01:00-02:00 UTC)
+        let sunday = [Weekday::Sun];
+        let w = window("02:30", "04:00");
+        assert!(!w.contains(&sunday, utc("2026-10-25 00:29:59")));
+        assert!(w.contains(&sunday, utc("2026-10-25 00:30:00")));
+        assert!(w.contains(&sunday, utc("2026-10-25 01:45:00")));
+        assert!(!w.contains(&sunday, utc("2026-10-25 03:00:00")));
+
+        // Bitiş de ilk 02:30'dur; saat geri alınınca aralık yeniden başlamaz
+        let w = window("01:00", "02:30");
+        assert!(w.contains(&sunday, utc("2026-10-25 00:15:00")));
+        assert!(!w.contains(&sunday, utc("2026-10-25 00:30:00")));
+        assert!(!w.contains(&sunday, utc("2026-10-25 01:15:00")));
+    }
+}